@@ -16,7 +16,6 @@ use {
 
 fn main() {
     let mut rng = ChaCha20Rng::from_entropy();
-    let mut transcript = Prover::new();
 
     println!("Hyrax commitment and opening:");
     for size_log2 in 10..24 {
@@ -24,6 +23,7 @@ fn main() {
         let cols = 1 << (size.ilog2() / 2);
         let rows = size / cols;
         let hyrax = HyraxCommiter::new(cols);
+        let mut transcript: Prover = Prover::with_domain(&hyrax.domain_separator());
         let f = (0..size).map(|_| rng.gen::<Fr>()).collect::<Vec<_>>();
         let a = (0..rows).map(|_| rng.gen()).collect::<Vec<Fr>>();
         let b = (0..cols).map(|_| rng.gen()).collect::<Vec<Fr>>();
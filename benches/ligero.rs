@@ -8,6 +8,8 @@ use {
     },
     ark_bn254::Fr,
     delegated_spartan::{
+        expander_code::ExpanderCode,
+        linear_code::LinearCode,
         pcs::{hyrax::compute_contraction, ligero::LigeroCommitter},
         poseidon,
         transcript::Prover,
@@ -19,56 +21,77 @@ use {
 
 fn main() {
     let mut rng = ChaCha20Rng::from_entropy();
-    let mut transcript = Prover::new();
 
     println!("Ligero commitment and opening:");
     for size_log2 in 10..24 {
-        let size: usize = 1 << size_log2;
+        let committer = LigeroCommitter::new(128.0, 1 << size_log2);
+        bench(&mut rng, "reed-solomon", size_log2, &committer);
 
-        let committer = LigeroCommitter::new(128.0, size);
-        let f = (0..size).map(|_| rng.gen::<Fr>()).collect::<Vec<_>>();
-        let a = (0..committer.rows).map(|_| rng.gen()).collect::<Vec<Fr>>();
-        let b = (0..committer.cols).map(|_| rng.gen()).collect::<Vec<Fr>>();
-        let c = compute_contraction(&f, &a, &b);
-        let mut num_hashes = (0, 0);
-        let mut mem = (0, 0);
+        // Same matrix shape with a linear-time encodable code. Skipped while the
+        // queries exceed the codeword length, as all columns would be opened.
+        let committer =
+            LigeroCommitter::with_code(128.0, committer.rows, ExpanderCode::new(committer.cols));
+        if committer.queries < committer.code.codeword_length() {
+            bench(&mut rng, "expander", size_log2, &committer);
+        }
+    }
+}
 
-        let duration = time({
-            let transcript = &mut transcript;
-            let mem = &mut mem;
-            let num_hashes = &mut num_hashes;
-            || {
-                let before = (
-                    poseidon::COUNT_3.load(Ordering::SeqCst),
-                    poseidon::COUNT_16.load(Ordering::SeqCst),
-                    ALLOCATOR.count(),
-                    ALLOCATOR.max(),
-                );
-                transcript.proof.clear();
-                let s = committer.commit(transcript, black_box(&f));
-                transcript.write(c);
-                s.prove_contraction(transcript, &a, &b);
-                *num_hashes = (
-                    poseidon::COUNT_3.load(Ordering::SeqCst) - before.0,
-                    poseidon::COUNT_16.load(Ordering::SeqCst) - before.1,
-                );
-                *mem = (ALLOCATOR.count() - before.2, ALLOCATOR.max());
-            }
-        });
-        let proof_size = transcript.proof.len() * size_of::<Fr>();
+fn bench<C: LinearCode>(
+    rng: &mut impl Rng,
+    name: &str,
+    size_log2: usize,
+    committer: &LigeroCommitter<C>,
+) {
+    let size: usize = 1 << size_log2;
+    let f = (0..size).map(|_| rng.gen::<Fr>()).collect::<Vec<_>>();
+    let a = (0..committer.rows).map(|_| rng.gen()).collect::<Vec<Fr>>();
+    let b = (0..committer.cols).map(|_| rng.gen()).collect::<Vec<Fr>>();
+    let c = compute_contraction(&f, &a, &b);
+    let mut transcript: Prover = Prover::with_domain(&committer.domain_separator());
+    let mut num_hashes = (0, 0, 0);
+    let mut mem = (0, 0);
 
-        println!(
-            "size: 2^{size_log2} = {}𝔽 = {:#}x{:#}, prover time: {}s, througput: {}𝔽/s, proof size: {}B, permute_3: {:#}, permute_16: {:#}, memory: {}B, allocs: {:#}",
-            human(size),
-            human(committer.rows),
-            human(committer.cols),
-            human(duration),
-            human(size as f64 / duration),
-            human(proof_size),
-            human(num_hashes.0),
-            human(num_hashes.1),
-            human(mem.1),
-            human(mem.0)
-        );
-    }
+    let duration = time({
+        let transcript = &mut transcript;
+        let mem = &mut mem;
+        let num_hashes = &mut num_hashes;
+        || {
+            ALLOCATOR.reset();
+            let before = (
+                poseidon::COUNT_3.load(Ordering::SeqCst),
+                poseidon::COUNT_16.load(Ordering::SeqCst),
+                poseidon::COUNT_24.load(Ordering::SeqCst),
+                ALLOCATOR.count(),
+                ALLOCATOR.max(),
+            );
+            transcript.proof.clear();
+            let s = committer.commit(transcript, black_box(&f));
+            transcript.write(c);
+            s.prove_contraction(transcript, &a, &b);
+            *num_hashes = (
+                poseidon::COUNT_3.load(Ordering::SeqCst) - before.0,
+                poseidon::COUNT_16.load(Ordering::SeqCst) - before.1,
+                poseidon::COUNT_24.load(Ordering::SeqCst) - before.2,
+            );
+            *mem = (ALLOCATOR.count() - before.3, ALLOCATOR.max());
+        }
+    });
+    let proof_size = transcript.proof.len() * size_of::<Fr>();
+
+    println!(
+        "{name}: size: 2^{size_log2} = {}𝔽 = {:#}x{:#}, queries: {}, prover time: {}s, througput: {}𝔽/s, proof size: {}B, permute_3: {:#}, permute_16: {:#}, permute_24: {:#}, memory: {}B, allocs: {:#}",
+        human(size),
+        human(committer.rows),
+        human(committer.cols),
+        committer.queries,
+        human(duration),
+        human(size as f64 / duration),
+        human(proof_size),
+        human(num_hashes.0),
+        human(num_hashes.1),
+        human(num_hashes.2),
+        human(mem.1),
+        human(mem.0)
+    );
 }
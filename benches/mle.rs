@@ -2,7 +2,7 @@ use {
     ark_bn254::Fr,
     criterion::{black_box, criterion_group, criterion_main, Criterion},
     delegated_spartan::{
-        sum_check::{eval_mle, par_eval_mle, prove_sumcheck},
+        sum_check::{domain_separator, eval_mle, par_eval_mle, prove_sumcheck},
         transcript::Prover,
     },
     rand::{Rng, SeedableRng},
@@ -32,9 +32,10 @@ fn bench_prove_sumcheck(c: &mut Criterion) {
     let mut rng = ChaCha20Rng::from_entropy();
     let mut f = (0..1 << SIZE).map(|_| rng.gen::<Fr>()).collect::<Vec<_>>();
     let sum = f.iter().sum();
+    let separator = domain_separator(SIZE, 1);
     c.bench_function("prove_sumcheck", |b| {
         b.iter(|| {
-            let mut transcript = Prover::new();
+            let mut transcript: Prover = Prover::with_domain(&separator);
             prove_sumcheck(&mut transcript, SIZE, &mut f, sum);
             transcript.finish()
         })
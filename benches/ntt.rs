@@ -2,7 +2,7 @@ use {
     ark_bn254::Fr,
     ark_poly::{EvaluationDomain, Radix2EvaluationDomain},
     criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput},
-    delegated_spartan::ntt::{intt, ntt},
+    delegated_spartan::ntt::{intt, ntt, NttPlan},
 };
 
 fn bench_ntt(c: &mut Criterion) {
@@ -17,6 +17,10 @@ fn bench_ntt(c: &mut Criterion) {
         group.bench_function(BenchmarkId::new("intt", size), |b| {
             b.iter(|| intt(&mut input))
         });
+        let plan = NttPlan::new(size as usize);
+        group.bench_function(BenchmarkId::new("plan", size), |b| {
+            b.iter(|| plan.ntt(&mut input))
+        });
     }
 }
 
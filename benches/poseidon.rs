@@ -20,6 +20,20 @@ fn bench_poseidon(c: &mut Criterion) {
         b.iter(|| poseidon::permute_16(&mut state))
     });
 
+    let mut states: Vec<[Fr; 16]> = (0..64)
+        .map(|j| array::from_fn(|i| Fr::from((16 * j + i) as u64)))
+        .collect();
+    group.throughput(Throughput::Elements(16 * 64));
+    group.bench_function(BenchmarkId::new("permute_many", 16), |b| {
+        b.iter(|| poseidon::permute_16_many(&mut states))
+    });
+
+    let mut state: [Fr; 24] = array::from_fn(|i| Fr::from(i as u64));
+    group.throughput(Throughput::Elements(24));
+    group.bench_function(BenchmarkId::new("permute", 24), |b| {
+        b.iter(|| poseidon::permute_24(&mut state))
+    });
+
     for size in [100, 1000, 1024, 2048, 4096, 10_000] {
         let input: Vec<Fr> = (0_u64..size).map(Fr::from).collect();
         group.throughput(Throughput::Elements(size));
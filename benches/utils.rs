@@ -1,7 +1,6 @@
 use {
     num_traits::AsPrimitive,
     std::{
-        cmp::Ordering,
         fmt::{self, Display, Formatter},
         hint::black_box,
         time::{Duration, Instant},
@@ -1,9 +1,7 @@
 use {
     ark_bn254::Fr,
     ark_ff::Field,
-    ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate},
-    rayon::iter::repeatn,
-    serde::{Deserialize, Serialize},
+    serde::Deserialize,
     std::{fs::File, str::FromStr},
 };
 
@@ -0,0 +1,228 @@
+//! Linear-time encodable code in the style of Brakedown's Spielman-type code.
+//!
+//! A message $x$ of length $n$ is encoded as $(x, z, v)$ where $y = A ⋅ x$ has
+//! length $α n$, $z$ is the recursive encoding of $y$ and $v = B ⋅ z$. Both
+//! $A$ and $B$ are sparse random matrices with the column degrees $c_n$ and $d_n$
+//! of Brakedown figure 2. Short messages are Reed-Solomon encoded.
+//!
+//! # References
+//!
+//! * Alexander Golovnev, Jonathan Lee, Srinath Setty, Justin Thaler, Riad S. Wahby (2021).
+//!   Brakedown: Linear-time and field-agnostic SNARKs for R1CS.
+//!   <https://eprint.iacr.org/2021/1043>
+use {
+    crate::{
+        linear_code::LinearCode, ntt::smooth_size, reed_solomon::ReedSolomon,
+        sparse_matrix::SparseMatrix,
+    },
+    ark_bn254::Fr,
+    ark_ff::PrimeField,
+    rand::{seq::index::sample, Rng, SeedableRng},
+    rand_chacha::ChaCha20Rng,
+};
+
+const SEED: [u8; 32] = *b"expander_code::ExpanderCode::new";
+
+// Parameters from Brakedown figure 2.
+const ALPHA: f64 = 0.1195;
+const BETA: f64 = 0.0248;
+const R: f64 = 1.42;
+
+/// Messages up to this length are Reed-Solomon encoded.
+const BASE_LENGTH: usize = 32;
+
+pub struct ExpanderCode {
+    message_length: usize,
+    codeword_length: usize,
+    levels: Vec<Level>,
+    /// Reed-Solomon code for the last level, on an NTT friendly domain.
+    base: ReedSolomon,
+}
+
+struct Level {
+    a: SparseMatrix,
+    b: SparseMatrix,
+}
+
+impl ExpanderCode {
+    pub fn new(message_length: usize) -> Self {
+        assert!(message_length > 0);
+        // Matrices are PRNG generated, so prover and verifier agree on them.
+        let mut rng = ChaCha20Rng::from_seed(SEED);
+
+        // Message lengths at each level, the top level always recurses so the
+        // code is systematic.
+        let mut lengths = vec![message_length];
+        while lengths.len() == 1 || *lengths.last().unwrap() > BASE_LENGTH {
+            let n = *lengths.last().unwrap();
+            lengths.push((ALPHA * n as f64).ceil() as usize);
+        }
+
+        // Codeword lengths from the bottom up.
+        let base = *lengths.last().unwrap();
+        let base = ReedSolomon::new(base, smooth_size((R * base as f64).ceil() as usize));
+        let mut codeword_length = base.codeword_length();
+        let mut levels = Vec::with_capacity(lengths.len() - 1);
+        for window in lengths.windows(2).rev() {
+            let (n, m) = (window[0], window[1]);
+            let parity = (((R - 1.0 - R * ALPHA) * n as f64).ceil() as usize).max(1);
+            levels.push(Level {
+                a: random_matrix(&mut rng, m, n, degree_a(n)),
+                b: random_matrix(&mut rng, parity, codeword_length, degree_b(n)),
+            });
+            codeword_length += n + parity;
+        }
+        levels.reverse();
+
+        Self {
+            message_length,
+            codeword_length,
+            levels,
+            base,
+        }
+    }
+
+    fn encode_level(&self, level: usize, message: &[Fr], codeword: &mut [Fr]) {
+        let Some(Level { a, b }) = self.levels.get(level) else {
+            self.base.encode(message, codeword);
+            return;
+        };
+        let (x, tail) = codeword.split_at_mut(message.len());
+        let (z, v) = tail.split_at_mut(b.cols);
+        x.copy_from_slice(message);
+        self.encode_level(level + 1, &a.mul_left(message), z);
+        v.copy_from_slice(&b.mul_left(z));
+    }
+}
+
+impl LinearCode for ExpanderCode {
+    fn message_length(&self) -> usize {
+        self.message_length
+    }
+
+    fn codeword_length(&self) -> usize {
+        self.codeword_length
+    }
+
+    /// Relative distance $β / r$ as shown in Brakedown for the figure 2 degrees,
+    /// which holds except with probability about $2^{-100}$ over the matrices.
+    fn distance(&self) -> usize {
+        ((BETA * self.message_length as f64).floor() as usize).max(1)
+    }
+
+    fn systematic_index(&self, i: usize) -> usize {
+        assert!(i < self.message_length);
+        i
+    }
+
+    fn encode_systematic(&self, message: &[Fr], codeword: &mut [Fr]) {
+        assert_eq!(message.len(), self.message_length);
+        assert_eq!(codeword.len(), self.codeword_length);
+        self.encode_level(0, message, codeword);
+    }
+}
+
+/// Non-zeros per column of `A` at message length `n`, $c_n$ in Brakedown figure 2.
+fn degree_a(n: usize) -> usize {
+    let n = n as f64;
+    let expansion = (1.28 * BETA * n).ceil().max((BETA * n).ceil() + 4.0);
+    let bound = (110.0 / n + entropy(BETA) + ALPHA * entropy(1.28 * BETA / ALPHA))
+        / (BETA * (ALPHA / (1.28 * BETA)).log2());
+    expansion.min(bound.ceil()) as usize
+}
+
+/// Non-zeros per column of `B` at message length `n`, $d_n$ in Brakedown figure 2.
+fn degree_b(n: usize) -> usize {
+    let n = n as f64;
+    let mu = R - 1.0 - R * ALPHA;
+    let nu = BETA + ALPHA * BETA + 0.03;
+    let field_bits = Fr::MODULUS_BIT_SIZE as f64;
+    let expansion = (2.0 * BETA + (R - 1.0 + 110.0 / n) / field_bits) * n;
+    let bound = (R * ALPHA * entropy(BETA / R) + mu * entropy(nu / mu) + 110.0 / n)
+        / (ALPHA * BETA * (mu / nu).log2());
+    expansion.ceil().min(bound.ceil()) as usize
+}
+
+/// Binary entropy function.
+fn entropy(p: f64) -> f64 {
+    -p * p.log2() - (1.0 - p) * (1.0 - p).log2()
+}
+
+/// Random `rows` × `cols` matrix with `degree` non-zeros in each column.
+fn random_matrix(rng: &mut impl Rng, rows: usize, cols: usize, degree: usize) -> SparseMatrix {
+    let degree = degree.min(rows);
+    let mut entries = Vec::with_capacity(cols * degree);
+    for col in 0..cols {
+        for row in sample(rng, rows, degree) {
+            entries.push((row * cols + col, rng.gen()));
+        }
+    }
+    SparseMatrix {
+        rows,
+        cols,
+        entries,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, ark_ff::Field};
+
+    #[test]
+    fn test_linear() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for n in [1, 10, 32, 33, 1000] {
+            let code = ExpanderCode::new(n);
+            assert!(code.codeword_length() > n);
+            let x: Vec<Fr> = (0..n).map(|_| rng.gen()).collect();
+            let y: Vec<Fr> = (0..n).map(|_| rng.gen()).collect();
+            let s: Fr = rng.gen();
+            let mut cx = vec![Fr::ZERO; code.codeword_length()];
+            let mut cy = vec![Fr::ZERO; code.codeword_length()];
+            let mut cs = vec![Fr::ZERO; code.codeword_length()];
+            code.encode_systematic(&x, &mut cx);
+            code.encode_systematic(&y, &mut cy);
+            let xy: Vec<Fr> = x.iter().zip(&y).map(|(x, y)| *x + s * y).collect();
+            code.encode_systematic(&xy, &mut cs);
+            assert_eq!(&cx[..n], &x[..]);
+            assert!(cs
+                .iter()
+                .zip(cx.iter().zip(&cy))
+                .all(|(c, (x, y))| *c == *x + s * y));
+        }
+    }
+    #[test]
+    fn test_degrees() {
+        // Large messages approach the asymptotic degrees.
+        assert_eq!((degree_a(100), degree_b(100)), (7, 6));
+        assert_eq!((degree_a(1 << 20), degree_b(1 << 20)), (6, 35));
+    }
+
+    #[test]
+    fn test_distance() {
+        // Sparse and random messages all encode to words of at least the claimed weight.
+        let mut rng = ChaCha20Rng::from_entropy();
+        let n = 500;
+        let code = ExpanderCode::new(n);
+        assert_eq!(code.distance(), 12);
+        let mut codeword = vec![Fr::ZERO; code.codeword_length()];
+        let mut check = |message: &[Fr]| {
+            code.encode_systematic(message, &mut codeword);
+            let weight = codeword.iter().filter(|c| **c != Fr::ZERO).count();
+            assert!(weight >= code.distance());
+        };
+        let mut message = vec![Fr::ZERO; n];
+        for i in 0..n {
+            message[i] = rng.gen();
+            check(&message);
+            let j = rng.gen_range(0..n);
+            message[j] = rng.gen();
+            check(&message);
+            message.fill(Fr::ZERO);
+        }
+        for _ in 0..10 {
+            message.fill_with(|| rng.gen());
+            check(&message);
+        }
+    }
+}
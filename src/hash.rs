@@ -0,0 +1,111 @@
+//! Byte oriented hash functions for native and EVM verifiers.
+//!
+//! Field elements are encoded as 32 byte big-endian canonical integers, the
+//! same as `uint256` in the EVM ABI.
+use {
+    ark_bn254::Fr,
+    ark_ff::{BigInt, BigInteger, PrimeField},
+    sha2::Digest,
+    std::array,
+};
+
+/// A hash function with 32 byte output.
+pub trait ByteHash: Send + Sync {
+    fn hash(data: &[u8]) -> [u8; 32];
+}
+
+/// Keccak-256 as in the EVM `KECCAK256` opcode, not SHA3-256.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Keccak256;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sha256;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Blake3;
+
+impl ByteHash for Keccak256 {
+    fn hash(data: &[u8]) -> [u8; 32] {
+        sha3::Keccak256::digest(data).into()
+    }
+}
+
+impl ByteHash for Sha256 {
+    fn hash(data: &[u8]) -> [u8; 32] {
+        sha2::Sha256::digest(data).into()
+    }
+}
+
+impl ByteHash for Blake3 {
+    fn hash(data: &[u8]) -> [u8; 32] {
+        blake3::hash(data).into()
+    }
+}
+
+pub fn encode(value: Fr) -> [u8; 32] {
+    value.into_bigint().to_bytes_be().try_into().unwrap()
+}
+
+/// Encodings of `values`, concatenated.
+pub fn encode_all(values: &[Fr]) -> Vec<u8> {
+    values.iter().copied().flat_map(encode).collect()
+}
+
+/// Decode a digest as a uniform field element by rejection sampling.
+///
+/// The top two bits are cleared and the result is rejected if it is not less
+/// than the modulus, which happens with probability ~24%. Accepted values are
+/// exactly uniform if the digest is.
+pub fn decode_uniform(mut digest: [u8; 32]) -> Option<Fr> {
+    digest[0] &= 0x3f;
+    let words =
+        array::from_fn(|i| u64::from_be_bytes(digest[24 - 8 * i..32 - 8 * i].try_into().unwrap()));
+    Fr::from_bigint(BigInt(words))
+}
+
+/// Reduce a digest modulo the field size. This is biased, so only suitable
+/// where collision resistance is all that matters.
+pub fn decode_reduced(digest: [u8; 32]) -> Fr {
+    Fr::from_be_bytes_mod_order(&digest)
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        ark_ff::Field,
+        rand::{Rng, SeedableRng},
+        rand_chacha::ChaCha20Rng,
+    };
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(
+            hex::encode(Keccak256::hash(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex::encode(Sha256::hash(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex::encode(Blake3::hash(b"")),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for _ in 0..100 {
+            let x: Fr = rng.gen();
+            assert_eq!(decode_uniform(encode(x)), Some(x));
+            assert_eq!(decode_reduced(encode(x)), x);
+        }
+        assert_eq!(encode(Fr::ONE)[31], 1);
+        assert_eq!(decode_uniform([0xff; 32]), None);
+        let mut masked = [0xc0; 32];
+        masked[0] = 0;
+        assert_eq!(decode_uniform([0xc0; 32]), Some(decode_reduced(masked)));
+    }
+}
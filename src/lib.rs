@@ -1,17 +1,19 @@
+pub mod expander_code;
+pub mod hash;
+pub mod linear_code;
 pub mod merkle_tree;
 pub mod ntt;
 pub mod pcs;
+pub mod polynomial;
 pub mod poseidon;
+pub mod r1cs;
 pub mod reed_solomon;
+pub mod sparse_matrix;
 pub mod sum_check;
 pub mod transcript;
 
 use {
-    crate::transcript::{Prover, Verifier},
-    ark_bn254::Fr,
-    ark_ff::Zero,
-    pcs::hyrax::HyraxCommiter,
-    rand::Rng,
+    crate::transcript::Prover, ark_bn254::Fr, ark_ff::Zero, pcs::hyrax::HyraxCommiter, rand::Rng,
 };
 
 pub fn prove_r1cs(
@@ -34,7 +36,7 @@ pub fn prove_r1cs(
     let _cz = vec![Fr::zero(); size];
 
     // Compute MLE of eq(r, x)
-    let _e = todo!();
+    let _e: Vec<Fr> = todo!();
 
     // Prove the sum equals zero
     // let (r, rs) = prove_sumcheck_r1cs(transcript, size, e, az, bz, cz, Fr::zero());
@@ -0,0 +1,21 @@
+//! Linear codes used by the tensor polynomial commitment schemes.
+use ark_bn254::Fr;
+
+pub trait LinearCode {
+    fn message_length(&self) -> usize;
+
+    fn codeword_length(&self) -> usize;
+
+    /// Minimum Hamming distance between distinct codewords.
+    fn distance(&self) -> usize;
+
+    fn relative_distance(&self) -> f64 {
+        self.distance() as f64 / self.codeword_length() as f64
+    }
+
+    /// Position of the `i`-th message symbol in a systematic codeword.
+    fn systematic_index(&self, i: usize) -> usize;
+
+    /// Systematic encoding, `codeword[systematic_index(i)] == message[i]`.
+    fn encode_systematic(&self, message: &[Fr], codeword: &mut [Fr]);
+}
@@ -1,78 +1,475 @@
 use {
     crate::{
-        poseidon::compress,
-        transcript::{Prover, Verifier},
+        hash::{decode_reduced, encode_all, ByteHash},
+        poseidon::{
+            compress_with_domain, permute_24, permute_24_many, permute_3, permute_3_many,
+            HashDomain, POSEIDON2_12, POSEIDON2_28, POSEIDON2_8,
+        },
+        transcript::{DuplexSponge, Prover, Verifier},
     },
     ark_bn254::Fr,
     ark_ff::Field,
-    std::{array, iter::repeat},
+    rand::Rng,
+    rayon::prelude::*,
+    std::{fmt::Debug, marker::PhantomData},
+    thiserror::Error,
 };
 
-// TODO: Determine optimal arity for proof-size / verifier complexity trade-off.
-const ARITY: usize = 16;
+/// Supported arities, see [`MerkleConfig::arity`].
+pub const ARITIES: [usize; 5] = [2, 4, 8, 16, 24];
 
-pub struct MerkleTree(Vec<Vec<Fr>>);
+const MAX_ARITY: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MerkleConfig<H: MerkleHash = Poseidon2Hash> {
+    /// Salt each leaf with prover randomness, so that the root and revealed
+    /// siblings leak nothing about unopened leaves. Salts of opened leaves
+    /// are revealed with the authentication path.
+    pub hiding: bool,
+    /// Number of children per node, one of [`ARITIES`]. Higher arity means
+    /// fewer hashes for the verifier but more siblings per opening.
+    pub arity: usize,
+    /// Number of top layers to commit to as a cap instead of a single root.
+    /// Openings stop at the cap of up to `arity^cap_height` nodes.
+    pub cap_height: usize,
+    /// Hash function for leaves and nodes.
+    pub hash: PhantomData<H>,
+    /// HashDomain of leaf vector hashes, [`HashDomain::Column`] for matrix commitments.
+    pub leaf_domain: HashDomain,
+}
+
+/// Hash functions for Merkle trees. [`Poseidon2Hash`] is cheap to verify in
+/// circuits, the byte oriented [`ByteHash`] functions are cheap natively and
+/// in the EVM.
+pub trait MerkleHash: Copy + Eq + Debug + Send + Sync {
+    /// Hash leaf vectors and salts in `domain`.
+    fn hash(domain: HashDomain, values: &[Fr]) -> Fr;
+
+    /// Hash the children of a node, zero padded to `arity`.
+    fn compress(arity: usize, family: &[Fr]) -> Fr;
+
+    /// Hash consecutive families of `nodes` into `parents`, in parallel.
+    fn compress_layer(arity: usize, nodes: &[Fr], parents: &mut [Fr]) {
+        compress_layer_parallel::<Self>(arity, nodes, parents);
+    }
+}
+
+/// Poseidon2. Nodes use the truncated permutation of the smallest width that
+/// fits the children and a domain tag: 3, 8, 12, 24 and 28 for the [`ARITIES`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Poseidon2Hash;
+
+impl<H: MerkleHash> Default for MerkleConfig<H> {
+    fn default() -> Self {
+        Self {
+            hiding: false,
+            arity: 16,
+            cap_height: 0,
+            hash: PhantomData,
+            leaf_domain: HashDomain::Leaf,
+        }
+    }
+}
+
+impl<H: MerkleHash> MerkleConfig<H> {
+    /// Maximum number of nodes in the cap.
+    pub fn cap_width(&self) -> usize {
+        u32::try_from(self.cap_height)
+            .ok()
+            .and_then(|height| self.arity.checked_pow(height))
+            .unwrap_or(usize::MAX)
+    }
+}
+
+pub struct MerkleTree<H: MerkleHash = Poseidon2Hash> {
+    config: MerkleConfig<H>,
+    /// Number of field elements in each leaf.
+    leaf_size: usize,
+    /// Leaf vectors, concatenated.
+    data: Vec<Fr>,
+    salts: Vec<Fr>,
+    /// Leaf hashes, followed by the salted leaves if hiding, followed by the
+    /// node layers up to the root, in one flat buffer.
+    nodes: Vec<Fr>,
+    /// Start of each layer in `nodes`, followed by `nodes.len()`.
+    layers: Vec<usize>,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Merkle opening indices must be sorted, unique and in range.")]
+    InvalidIndices,
+    #[error("Merkle opening failed to verify.")]
+    MerkleVerificationFailed,
+}
 
-/// TODO: Blinding?
 impl MerkleTree {
+    /// Tree over single field element leaves.
     pub fn new(leaves: Vec<Fr>) -> Self {
-        // TODO: Maybe flatten the tree to a single vector?
-        let mut tree: Vec<Vec<Fr>> = vec![leaves];
-        loop {
-            let leaves = &tree.last().unwrap();
-            if leaves.len() == 1 {
-                break;
+        Self::with_leaf_size(1, leaves)
+    }
+
+    /// Tree over the vectors `leaves.chunks_exact(leaf_size)`.
+    pub fn with_leaf_size(leaf_size: usize, leaves: Vec<Fr>) -> Self {
+        Self::with_public_config(MerkleConfig::default(), leaf_size, leaves)
+    }
+}
+
+impl<H: MerkleHash> MerkleTree<H> {
+    /// Tree with a non-hiding `config`, which needs no randomness.
+    pub fn with_public_config(config: MerkleConfig<H>, leaf_size: usize, leaves: Vec<Fr>) -> Self {
+        assert!(!config.hiding, "Hiding trees need randomness.");
+        Self::build(config, leaf_size, Vec::new(), leaves)
+    }
+
+    pub fn with_config(
+        rng: &mut impl Rng,
+        config: MerkleConfig<H>,
+        leaf_size: usize,
+        leaves: Vec<Fr>,
+    ) -> Self {
+        let salts = if config.hiding {
+            (0..leaves.len() / leaf_size).map(|_| rng.gen()).collect()
+        } else {
+            Vec::new()
+        };
+        Self::build(config, leaf_size, salts, leaves)
+    }
+
+    fn build(config: MerkleConfig<H>, leaf_size: usize, salts: Vec<Fr>, data: Vec<Fr>) -> Self {
+        assert!(ARITIES.contains(&config.arity), "Unsupported Merkle arity.");
+        assert!(
+            leaf_size > 0 && data.len().is_multiple_of(leaf_size),
+            "Invalid leaf size."
+        );
+        let size = data.len() / leaf_size;
+
+        // Compute the layer boundaries.
+        let mut layers = vec![0, size];
+        if config.hiding {
+            layers.push(2 * size);
+        }
+        let mut width = size;
+        while width > 1 {
+            width = width.div_ceil(config.arity);
+            layers.push(layers.last().unwrap() + width);
+        }
+
+        // Hash the leaves, including single element leaves.
+        let mut nodes = Vec::with_capacity(*layers.last().unwrap());
+        data.par_chunks_exact(leaf_size)
+            .map(|leaf| H::hash(config.leaf_domain, leaf))
+            .collect_into_vec(&mut nodes);
+
+        // Hash the layers in place, each layer in parallel.
+        nodes.resize(*layers.last().unwrap(), Fr::ZERO);
+        for (i, bounds) in layers.windows(3).enumerate() {
+            let (previous, next) = nodes[bounds[0]..bounds[2]].split_at_mut(bounds[1] - bounds[0]);
+            if config.hiding && i == 0 {
+                next.par_iter_mut()
+                    .zip(previous.par_iter().zip(&salts))
+                    .for_each(|(node, (leaf, s))| *node = H::hash(HashDomain::Salt, &[*leaf, *s]));
+            } else {
+                H::compress_layer(config.arity, previous, next);
             }
-            let layer = leaves.chunks(ARITY).map(compress).collect();
-            tree.push(layer);
         }
-        Self(tree)
+
+        Self {
+            config,
+            leaf_size,
+            data,
+            salts,
+            nodes,
+            layers,
+        }
+    }
+
+    pub fn config(&self) -> MerkleConfig<H> {
+        self.config
     }
 
     pub fn root(&self) -> Fr {
-        self.0.last().unwrap().first().copied().unwrap()
+        self.nodes.last().copied().unwrap()
     }
 
+    /// The first layer with at most [`MerkleConfig::cap_width`] nodes. This is
+    /// the root if `cap_height` is zero.
+    pub fn cap(&self) -> &[Fr] {
+        self.path_layers()
+            .find(|layer| layer.len() <= self.config.cap_width())
+            .unwrap()
+    }
+
+    /// Number of leaves.
+    pub fn size(&self) -> usize {
+        self.layers[1]
+    }
+
+    pub fn leaf_size(&self) -> usize {
+        self.leaf_size
+    }
+
+    /// Leaf vectors, concatenated.
     pub fn leaves(&self) -> &[Fr] {
-        self.0.first().unwrap()
+        &self.data
+    }
+
+    pub fn leaf(&self, index: usize) -> &[Fr] {
+        &self.leaves()[index * self.leaf_size..(index + 1) * self.leaf_size]
+    }
+
+    /// Layers that authentication paths run through.
+    fn path_layers(&self) -> impl Iterator<Item = &[Fr]> {
+        let skip = usize::from(self.config.hiding);
+        self.layers[skip..]
+            .windows(2)
+            .map(|bounds| &self.nodes[bounds[0]..bounds[1]])
+    }
+
+    pub fn reveal(&self, transcript: &mut Prover<impl DuplexSponge>, index: usize) {
+        self.reveal_many(transcript, &[index]);
     }
 
-    pub fn reveal(&self, transcript: &mut Prover, mut index: usize) {
-        for layer in self.0.iter() {
-            if layer.len() == 1 {
+    /// Reveal the leaves at a sorted set of `indices` and their authentication paths.
+    ///
+    /// The opened leaf contents are revealed first, followed by their salts in
+    /// hiding mode. Siblings shared between paths, or that are on a path
+    /// themselves, are revealed only once. Zero padding past the end of a layer
+    /// is implied.
+    pub fn reveal_many(&self, transcript: &mut Prover<impl DuplexSponge>, indices: &[usize]) {
+        assert!(
+            indices.windows(2).all(|w| w[0] < w[1]),
+            "Indices must be sorted and unique."
+        );
+        assert!(
+            indices.last().is_none_or(|&i| i < self.size()),
+            "Invalid index"
+        );
+        for &index in indices {
+            self.leaf(index)
+                .iter()
+                .for_each(|value| transcript.reveal(*value));
+        }
+        if self.config.hiding {
+            indices
+                .iter()
+                .for_each(|&i| transcript.reveal(self.salts[i]));
+        }
+        let arity = self.config.arity;
+        let mut indices = indices.to_vec();
+        for layer in self.path_layers() {
+            if layer.len() <= self.config.cap_width() {
                 break;
             }
-            let family = layer.chunks(ARITY).nth(index / ARITY).unwrap_or_default();
-            if family.len() < index % ARITY {
-                panic!("Invalid index");
+            for family in indices.chunk_by(|a, b| a / arity == b / arity) {
+                let start = family[0] / arity * arity;
+                let end = (start + arity).min(layer.len());
+                (start..end)
+                    .filter(|i| !family.contains(i))
+                    .for_each(|i| transcript.reveal(layer[i]));
             }
-            // Zero-pad the family and reveal siblings.
-            let family = family.iter().copied().chain(repeat(Fr::ZERO)).take(ARITY);
-            let siblings = family.enumerate().filter(|(i, _)| *i != index % ARITY);
-            siblings.for_each(|(_, sibling)| transcript.reveal(sibling));
-            index /= ARITY;
+            indices.iter_mut().for_each(|i| *i /= arity);
+            indices.dedup();
         }
     }
 }
 
-pub fn verify(transcript: &mut Verifier, root: Fr, mut index: usize, mut leaf: Fr) {
-    // TODO: Maybe pass tree size so we can terminate early on failure.
-    while leaf != root {
-        let family: [Fr; ARITY] = array::from_fn(|i| {
-            if i == index % ARITY {
-                leaf
-            } else {
-                transcript.reveal()
+/// Verify a single opening of a tree with `size` leaves of `leaf_size` elements
+/// against its cap, see [`MerkleTree::cap`]. Returns the leaf.
+pub fn verify(
+    transcript: &mut Verifier<impl DuplexSponge>,
+    config: MerkleConfig<impl MerkleHash>,
+    cap: &[Fr],
+    size: usize,
+    leaf_size: usize,
+    index: usize,
+) -> Result<Vec<Fr>, Error> {
+    verify_many(transcript, config, cap, size, leaf_size, &[index])
+}
+
+/// Verify the openings produced by [`MerkleTree::reveal_many`].
+/// Returns the opened leaves, concatenated.
+pub fn verify_many<H: MerkleHash>(
+    transcript: &mut Verifier<impl DuplexSponge>,
+    config: MerkleConfig<H>,
+    cap: &[Fr],
+    size: usize,
+    leaf_size: usize,
+    indices: &[usize],
+) -> Result<Vec<Fr>, Error> {
+    assert!(leaf_size > 0, "Invalid leaf size.");
+    if !indices.windows(2).all(|w| w[0] < w[1]) || indices.last().is_some_and(|&i| i >= size) {
+        return Err(Error::InvalidIndices);
+    }
+    let leaves: Vec<Fr> = (0..indices.len() * leaf_size)
+        .map(|_| transcript.reveal())
+        .collect();
+    let mut nodes: Vec<(usize, Fr)> = indices
+        .iter()
+        .copied()
+        .zip(
+            leaves
+                .chunks_exact(leaf_size)
+                .map(|leaf| H::hash(config.leaf_domain, leaf)),
+        )
+        .collect();
+    if config.hiding {
+        nodes
+            .iter_mut()
+            .for_each(|(_, leaf)| *leaf = H::hash(HashDomain::Salt, &[*leaf, transcript.reveal()]));
+    }
+    let arity = config.arity;
+    assert!(ARITIES.contains(&arity), "Unsupported Merkle arity.");
+    let mut width = size;
+    while width > config.cap_width() {
+        let mut parents = Vec::with_capacity(nodes.len());
+        for family in nodes.chunk_by(|(a, _), (b, _)| a / arity == b / arity) {
+            let parent = family[0].0 / arity;
+            let mut known = family.iter().peekable();
+            let mut children = [Fr::ZERO; MAX_ARITY];
+            for (i, child) in children.iter_mut().enumerate().take(arity) {
+                let index = parent * arity + i;
+                if let Some((_, node)) = known.next_if(|(j, _)| *j == index) {
+                    *child = *node;
+                } else if index < width {
+                    *child = transcript.reveal();
+                }
             }
-        });
-        leaf = compress(&family);
-        index /= ARITY;
+            parents.push((parent, H::compress(arity, &children[..arity])));
+        }
+        nodes = parents;
+        width = width.div_ceil(arity);
+    }
+    if cap.len() == width && nodes.iter().all(|(index, node)| cap[*index] == *node) {
+        Ok(leaves)
+    } else {
+        Err(Error::MerkleVerificationFailed)
+    }
+}
+
+impl MerkleHash for Poseidon2Hash {
+    fn hash(domain: HashDomain, values: &[Fr]) -> Fr {
+        compress_with_domain(domain, values)
+    }
+
+    /// Truncated Poseidon2 permutation of the smallest supported width above
+    /// `arity`, with the last state element set to [`HashDomain::Node`] tagged
+    /// with the arity.
+    fn compress(arity: usize, family: &[Fr]) -> Fr {
+        debug_assert!(family.len() <= arity);
+        match arity {
+            2 => {
+                let mut state = node_state::<3>(2, family);
+                permute_3(&mut state);
+                state[0]
+            }
+            4 => {
+                let mut state = node_state::<8>(4, family);
+                POSEIDON2_8.permute(&mut state);
+                state[0]
+            }
+            8 => {
+                let mut state = node_state::<12>(8, family);
+                POSEIDON2_12.permute(&mut state);
+                state[0]
+            }
+            16 => {
+                let mut state = node_state::<24>(16, family);
+                permute_24(&mut state);
+                state[0]
+            }
+            24 => {
+                let mut state = node_state::<28>(24, family);
+                POSEIDON2_28.permute(&mut state);
+                state[0]
+            }
+            _ => panic!("Unsupported Merkle arity."),
+        }
+    }
+
+    fn compress_layer(arity: usize, nodes: &[Fr], parents: &mut [Fr]) {
+        match arity {
+            2 => compress_batched(arity, permute_3_many, nodes, parents),
+            4 => compress_batched(arity, |s| POSEIDON2_8.permute_many(s), nodes, parents),
+            8 => compress_batched(arity, |s| POSEIDON2_12.permute_many(s), nodes, parents),
+            16 => compress_batched(arity, permute_24_many, nodes, parents),
+            24 => compress_batched(arity, |s| POSEIDON2_28.permute_many(s), nodes, parents),
+            _ => panic!("Unsupported Merkle arity."),
+        }
+    }
+}
+
+/// Zero padded `family` followed by the node tag for `arity` in the last element.
+fn node_state<const WIDTH: usize>(arity: usize, family: &[Fr]) -> [Fr; WIDTH] {
+    let mut state = [Fr::ZERO; WIDTH];
+    state[..family.len()].copy_from_slice(family);
+    state[WIDTH - 1] = HashDomain::Node.tag(arity);
+    state
+}
+
+/// Byte hash of the domain byte followed by the encoded values. Digests are
+/// reduced into the field, which only needs to preserve collision resistance.
+impl<H: ByteHash + Copy + Eq + Debug> MerkleHash for H {
+    fn hash(domain: HashDomain, values: &[Fr]) -> Fr {
+        let mut data = vec![domain as u8];
+        data.extend(encode_all(values));
+        decode_reduced(H::hash(&data))
+    }
+
+    fn compress(arity: usize, family: &[Fr]) -> Fr {
+        debug_assert!(family.len() <= arity);
+        let mut children = [Fr::ZERO; MAX_ARITY];
+        children[..family.len()].copy_from_slice(family);
+        <H as MerkleHash>::hash(HashDomain::Node, &children[..arity])
     }
 }
 
+fn compress_layer_parallel<H: MerkleHash>(arity: usize, nodes: &[Fr], parents: &mut [Fr]) {
+    parents
+        .par_iter_mut()
+        .zip(nodes.par_chunks(arity))
+        .for_each(|(parent, family)| *parent = H::compress(arity, family));
+}
+
+/// Same as [`Poseidon2Hash::compress`] for arity below `WIDTH`, but with
+/// batched permutations.
+fn compress_batched<const WIDTH: usize>(
+    arity: usize,
+    permute_many: impl Fn(&mut [[Fr; WIDTH]]) + Sync,
+    nodes: &[Fr],
+    parents: &mut [Fr],
+) {
+    const BATCH: usize = 16;
+    parents
+        .par_chunks_mut(BATCH)
+        .zip(nodes.par_chunks(BATCH * arity))
+        .for_each(|(parents, nodes)| {
+            let mut states = [[Fr::ZERO; WIDTH]; BATCH];
+            for (state, family) in states.iter_mut().zip(nodes.chunks(arity)) {
+                *state = node_state(arity, family);
+            }
+            let states = &mut states[..parents.len()];
+            permute_many(states);
+            parents
+                .iter_mut()
+                .zip(states.iter())
+                .for_each(|(parent, state)| *parent = state[0]);
+        });
+}
+
 #[cfg(test)]
 mod test {
-    use {super::*, std::mem::size_of};
+    use {
+        super::*,
+        crate::{
+            hash::{Blake3, Keccak256, Sha256},
+            transcript::ByteSponge,
+        },
+        ark_ff::MontFp,
+        rand::SeedableRng,
+        rand_chacha::ChaCha20Rng,
+        std::mem::size_of,
+    };
 
     #[test]
     fn test_merkle_tree_1000() {
@@ -83,8 +480,11 @@ mod test {
         // Proof
         let mut transcript = Prover::new();
         let tree = MerkleTree::new(leafs);
+        assert_eq!(
+            tree.root(),
+            MontFp!("4266283065690893755186795843895246814154647448907147387121544595523252908133")
+        );
         transcript.write(tree.root());
-        transcript.write(tree.leaves()[index]);
         tree.reveal(&mut transcript, index);
         let proof = transcript.finish();
         dbg!(proof.len() * size_of::<Fr>());
@@ -92,9 +492,228 @@ mod test {
         // Verify
         let mut transcript = Verifier::new(&proof);
         let vroot = transcript.read();
-        let vleaf = transcript.read();
         assert_eq!(vroot, tree.root());
-        assert_eq!(vleaf, leaf);
-        verify(&mut transcript, vroot, index, vleaf);
+        let config: MerkleConfig = MerkleConfig::default();
+        let vleaf = verify(&mut transcript, config, &[vroot], 1000, 1, index).unwrap();
+        assert_eq!(vleaf, [leaf]);
+    }
+
+    #[test]
+    fn test_merkle_tree_many() {
+        let leafs: Vec<_> = (0..1000).map(Fr::from).collect();
+        let indices = [3, 7, 200, 201, 531, 999];
+        let tree = MerkleTree::new(leafs.clone());
+        let config = tree.config();
+
+        // Proof
+        let mut transcript = Prover::new();
+        tree.reveal_many(&mut transcript, &indices);
+        let mut proof = transcript.finish();
+
+        // Shared siblings are revealed only once.
+        let mut transcript = Prover::new();
+        indices
+            .iter()
+            .for_each(|&i| tree.reveal(&mut transcript, i));
+        assert!(proof.len() < transcript.finish().len());
+
+        // Verify
+        let values: Vec<_> = indices.iter().map(|&i| leafs[i]).collect();
+        let mut transcript = Verifier::new(&proof);
+        let opened = verify_many(&mut transcript, config, &[tree.root()], 1000, 1, &indices);
+        assert_eq!(opened.unwrap(), values);
+
+        // Wrong leaf
+        proof[2] += Fr::ONE;
+        let mut transcript = Verifier::new(&proof);
+        assert!(verify_many(&mut transcript, config, &[tree.root()], 1000, 1, &indices).is_err());
+    }
+
+    #[test]
+    fn test_merkle_tree_vectors() {
+        let leafs: Vec<_> = (0..1000 * 20).map(Fr::from).collect();
+        let indices = [0, 12, 999];
+        let tree = MerkleTree::with_leaf_size(20, leafs.clone());
+        assert_eq!(tree.size(), 1000);
+        assert_eq!(tree.leaf(12), &leafs[240..260]);
+
+        // Leaf vectors do not commit like their concatenation.
+        assert_ne!(tree.root(), MerkleTree::new(leafs.clone()).root());
+        let other = MerkleTree::with_leaf_size(10, leafs.clone());
+        assert_ne!(tree.root(), other.root());
+        let config: MerkleConfig = MerkleConfig {
+            leaf_domain: HashDomain::Column,
+            ..MerkleConfig::default()
+        };
+        let columns = MerkleTree::with_public_config(config, 20, leafs.clone());
+        assert_ne!(tree.root(), columns.root());
+
+        let mut transcript = Prover::new();
+        tree.reveal_many(&mut transcript, &indices);
+        let mut proof = transcript.finish();
+
+        let config = tree.config();
+        let mut transcript = Verifier::new(&proof);
+        let opened = verify_many(&mut transcript, config, &[tree.root()], 1000, 20, &indices);
+        let values: Vec<_> = indices
+            .iter()
+            .flat_map(|&i| tree.leaf(i))
+            .copied()
+            .collect();
+        assert_eq!(opened.unwrap(), values);
+
+        // Wrong leaf element
+        proof[25] += Fr::ONE;
+        let mut transcript = Verifier::new(&proof);
+        assert!(verify_many(&mut transcript, config, &[tree.root()], 1000, 20, &indices).is_err());
+    }
+
+    #[test]
+    fn test_merkle_tree_hiding() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let config: MerkleConfig = MerkleConfig {
+            hiding: true,
+            ..MerkleConfig::default()
+        };
+        let leafs: Vec<_> = (0..300).map(Fr::from).collect();
+        let indices = [0, 17, 299];
+
+        // Same leaves commit to different roots.
+        let tree = MerkleTree::with_config(&mut rng, config, 1, leafs.clone());
+        let other = MerkleTree::with_config(&mut rng, config, 1, leafs.clone());
+        assert_ne!(tree.root(), other.root());
+        assert_ne!(tree.root(), MerkleTree::new(leafs.clone()).root());
+
+        let mut transcript = Prover::new();
+        tree.reveal_many(&mut transcript, &indices);
+        let proof = transcript.finish();
+
+        let values: Vec<_> = indices.iter().map(|&i| leafs[i]).collect();
+        let mut transcript = Verifier::new(&proof);
+        let opened = verify_many(&mut transcript, config, &[tree.root()], 300, 1, &indices);
+        assert_eq!(opened.unwrap(), values);
+
+        // Salts are required.
+        let mut transcript = Verifier::new(&proof);
+        let config: MerkleConfig = MerkleConfig::default();
+        assert!(verify_many(&mut transcript, config, &[tree.root()], 300, 1, &indices).is_err());
+    }
+
+    /// Opens a tree hashed with `H` and checks that a tree with `Other` rejects it.
+    fn check_hash<H: MerkleHash, Other: MerkleHash>() -> Fr {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let leafs: Vec<_> = (0..300).map(Fr::from).collect();
+        let indices = [0, 17, 99];
+        let values: Vec<_> = indices
+            .iter()
+            .flat_map(|&i| &leafs[3 * i..3 * i + 3])
+            .copied()
+            .collect();
+        let config = MerkleConfig::<H> {
+            hiding: true,
+            arity: 4,
+            ..MerkleConfig::default()
+        };
+        let tree = MerkleTree::with_config(&mut rng, config, 3, leafs.clone());
+        let mut transcript = Prover::with_sponge(ByteSponge::<Keccak256>::new());
+        tree.reveal_many(&mut transcript, &indices);
+        let proof = transcript.finish();
+
+        let mut transcript = Verifier::with_sponge(ByteSponge::<Keccak256>::new(), &proof);
+        let opened = verify_many(&mut transcript, config, &[tree.root()], 100, 3, &indices);
+        assert_eq!(opened.unwrap(), values);
+
+        // A different hash function does not verify.
+        let config = MerkleConfig::<Other> {
+            hiding: true,
+            arity: 4,
+            ..MerkleConfig::default()
+        };
+        let mut transcript = Verifier::with_sponge(ByteSponge::<Keccak256>::new(), &proof);
+        let result = verify_many(&mut transcript, config, &[tree.root()], 100, 3, &indices);
+        assert!(result.is_err());
+        tree.root()
+    }
+
+    #[test]
+    fn test_merkle_tree_hashes() {
+        let mut roots = vec![
+            check_hash::<Poseidon2Hash, Sha256>(),
+            check_hash::<Keccak256, Sha256>(),
+            check_hash::<Sha256, Blake3>(),
+            check_hash::<Blake3, Poseidon2Hash>(),
+        ];
+        roots.dedup();
+        assert_eq!(roots.len(), 4);
+    }
+
+    #[test]
+    fn test_merkle_tree_arity() {
+        let leafs: Vec<_> = (0..1000).map(Fr::from).collect();
+        let indices = [0, 1, 500, 999];
+        let values: Vec<_> = indices.iter().map(|&i| leafs[i]).collect();
+        let mut roots = Vec::new();
+        for arity in ARITIES {
+            let mut rng = ChaCha20Rng::from_entropy();
+            let config: MerkleConfig = MerkleConfig {
+                arity,
+                ..MerkleConfig::default()
+            };
+            let tree = MerkleTree::with_config(&mut rng, config, 1, leafs.clone());
+            let mut transcript = Prover::new();
+            tree.reveal_many(&mut transcript, &indices);
+            let proof = transcript.finish();
+
+            let mut transcript = Verifier::new(&proof);
+            let opened = verify_many(&mut transcript, config, &[tree.root()], 1000, 1, &indices);
+            assert_eq!(opened.unwrap(), values);
+            roots.push(tree.root());
+
+            // Changing any leaf or sibling fails.
+            for i in 0..proof.len() {
+                let mut tampered = proof.clone();
+                tampered[i] += Fr::ONE;
+                let mut transcript = Verifier::new(&tampered);
+                let result =
+                    verify_many(&mut transcript, config, &[tree.root()], 1000, 1, &indices);
+                assert!(result.is_err());
+            }
+        }
+        // Default arity is unchanged.
+        assert_eq!(roots[3], MerkleTree::new(leafs).root());
+        roots.dedup();
+        assert_eq!(roots.len(), ARITIES.len());
+    }
+
+    #[test]
+    fn test_merkle_tree_cap() {
+        let leafs: Vec<_> = (0..1000).map(Fr::from).collect();
+        let indices = [0, 1, 500, 999];
+        let values: Vec<_> = indices.iter().map(|&i| leafs[i]).collect();
+        let mut sizes = Vec::new();
+        for cap_height in 0..4 {
+            let mut rng = ChaCha20Rng::from_entropy();
+            let config: MerkleConfig = MerkleConfig {
+                arity: 4,
+                cap_height,
+                ..MerkleConfig::default()
+            };
+            let tree = MerkleTree::with_config(&mut rng, config, 1, leafs.clone());
+            assert_eq!(tree.cap().len(), [1, 4, 16, 63][cap_height]);
+            let mut transcript = Prover::new();
+            tree.reveal_many(&mut transcript, &indices);
+            let proof = transcript.finish();
+            sizes.push(proof.len());
+
+            let mut transcript = Verifier::new(&proof);
+            let opened = verify_many(&mut transcript, config, tree.cap(), 1000, 1, &indices);
+            assert_eq!(opened.unwrap(), values);
+
+            // Cap must match
+            let mut transcript = Verifier::new(&proof);
+            let cap = &tree.cap()[1..];
+            assert!(verify_many(&mut transcript, config, cap, 1000, 1, &indices).is_err());
+        }
+        assert!(sizes.windows(2).all(|w| w[0] > w[1]));
     }
 }
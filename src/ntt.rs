@@ -1,15 +1,20 @@
 //! NTT algorithms for the BN254 scalar field.
 //! Uses 5 as the generator of the multilicative group (same as arkworks).
 //! Fr^* is of order 2^28 * 3^2 * 13 * 29 * 983 * 11003 * 237073 * 405928799 * 1670836401704629 * 13818364434197438864469338081
-//! This NTT supports all divisors of 2^28 * 3^2 = 2415919104
+//! This NTT supports all divisors of 2^28 * 3^2 = 2415919104 directly, other divisors of the
+//! group order are supported using Bluestein's algorithm on top of a smooth size NTT.
+//! Twiddles are precomputed per size in an [`NttPlan`], which can be reused and
+//! shared between threads.
 //! TODO: 13 = 2^2 * 3^1 + 1 is a good candidate for Rader NTT and could also be supported.
 //! See also https://github.com/recmo/goldilocks/blob/main/ntt/src/ntt
 use {
     ark_bn254::Fr,
-    ark_ff::{Field, MontFp},
-    std::sync::RwLock,
+    ark_ff::{Field, MontFp, PrimeField},
 };
 
+/// Generator of the multiplicative group.
+pub const GENERATOR: Fr = MontFp!("5");
+
 /// Hardcoded roots of unity
 const HALF_OMEGA_3_1_PLUS_2: Fr =
     MontFp!("10944121435919637611123202872628637544274182200208017171849102093287904247808");
@@ -24,51 +29,172 @@ const OMEGA_8_3: Fr =
 const OMEGA_2415919104: Fr =
     MontFp!("8001236115608269688640730372558895144313937963023562728862538587154079436142");
 
-/// Cache of precomputed roots of unity.
-static ROOTS: RwLock<Vec<Fr>> = RwLock::new(Vec::new());
+/// Precomputed roots of unity for NTTs of a fixed size.
+pub struct NttPlan {
+    size: usize,
+    strategy: Strategy,
+}
 
-pub fn ntt(values: &mut [Fr]) {
-    if 2_415_919_104 % values.len() != 0 {
-        panic!("NTT size must be a divisor of 2415919104");
-    }
-    // Precompute more roots of unity if necessary.
-    let roots = ROOTS.read().unwrap();
-    let roots = if roots.is_empty() || roots.len() % values.len() != 0 {
-        // Obtain write lock to update the cache.
-        drop(roots);
-        let mut roots = ROOTS.write().unwrap();
-        // Race condition: check if another thread updated the cache.
-        if roots.is_empty() || roots.len() % values.len() != 0 {
-            // Minimal size to support all sizes seen so far.
-            let size = if roots.is_empty() {
-                values.len()
-            } else {
-                lcm(roots.len(), values.len())
-            };
-            roots.clear();
-            roots.reserve_exact(size);
+enum Strategy {
+    /// Mixed-radix NTT using powers of a root of unity.
+    Smooth(Vec<Fr>),
+    /// Bluestein's algorithm for sizes that are not a divisor of 2415919104.
+    Bluestein(Box<ChirpZPlan>),
+}
+
+/// Precomputed chirp-z transform. Evaluates polynomials with `inputs` coefficients
+/// on the `outputs` points $ρ^k$ using Bluestein's algorithm.
+pub struct ChirpZPlan {
+    inputs: usize,
+    outputs: usize,
+    plan: NttPlan,
+    // ρ^{-T(i)} for i < max(inputs, outputs), where T(i) = i ⋅ (i - 1) / 2.
+    chirp: Vec<Fr>,
+    // NTT of ρ^{T(i)} for i < inputs + outputs - 1.
+    kernel: Vec<Fr>,
+}
 
-            // Compute powers of roots of unity.
-            let root = root(size).unwrap();
+impl NttPlan {
+    pub fn new(size: usize) -> Self {
+        let root = root(size).expect("NTT size must be a divisor of the group order.");
+        let strategy = if 2_415_919_104 % size == 0 {
+            let mut roots = Vec::with_capacity(size);
             let mut root_i = Fr::ONE;
             while roots.len() < size {
                 roots.push(root_i);
                 root_i *= root;
             }
+            Strategy::Smooth(roots)
+        } else {
+            Strategy::Bluestein(Box::new(ChirpZPlan::new(size, size, root)))
+        };
+        Self { size, strategy }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Computes NTTs of consecutive chunks of `size` values.
+    pub fn ntt(&self, values: &mut [Fr]) {
+        assert_eq!(
+            values.len() % self.size(),
+            0,
+            "Values must be a whole number of NTTs."
+        );
+        match &self.strategy {
+            Strategy::Smooth(roots) => ntt_batch_inner(values, roots, self.size),
+            Strategy::Bluestein(plan) => {
+                let mut buffer = vec![Fr::ZERO; self.size];
+                for values in values.chunks_exact_mut(self.size) {
+                    plan.evaluate(values, &mut buffer);
+                    values.copy_from_slice(&buffer);
+                }
+            }
         }
-        // Back to read lock.
-        drop(roots);
-        ROOTS.read().unwrap()
-    } else {
-        roots
-    };
-    ntt_batch_inner(values, &roots, values.len());
+    }
+
+    /// Computes inverse NTTs of consecutive chunks of `size` values.
+    pub fn intt(&self, values: &mut [Fr]) {
+        let s = Fr::from(self.size() as u64).inverse().unwrap();
+        values.iter_mut().for_each(|v| *v *= s);
+        for values in values.chunks_exact_mut(self.size()) {
+            values[1..].reverse();
+        }
+        self.ntt(values);
+    }
+}
+
+impl ChirpZPlan {
+    pub fn new(inputs: usize, outputs: usize, ratio: Fr) -> Self {
+        assert!(inputs > 0 && outputs > 0);
+        // Using i ⋅ k = T(i + k) - T(i) - T(k) the transform becomes a correlation.
+        let len = inputs + outputs - 1;
+        let plan = NttPlan::new(smooth_size(len));
+        let ratio_inv = ratio.inverse().expect("Ratio must be non-zero.");
+        let mut chirp = Vec::with_capacity(inputs.max(outputs));
+        let mut kernel = vec![Fr::ZERO; plan.size()];
+        let (mut power, mut power_inv) = (Fr::ONE, Fr::ONE);
+        let (mut ratio_i, mut ratio_inv_i) = (Fr::ONE, Fr::ONE);
+        for (i, kernel) in kernel.iter_mut().take(len).enumerate() {
+            *kernel = power;
+            if i < chirp.capacity() {
+                chirp.push(power_inv);
+            }
+            power *= ratio_i;
+            power_inv *= ratio_inv_i;
+            ratio_i *= ratio;
+            ratio_inv_i *= ratio_inv;
+        }
+        plan.ntt(&mut kernel);
+        Self {
+            inputs,
+            outputs,
+            plan,
+            chirp,
+            kernel,
+        }
+    }
+
+    /// Evaluates the polynomial on the points $ρ^k$.
+    pub fn evaluate(&self, coefficients: &[Fr], evaluations: &mut [Fr]) {
+        assert_eq!(coefficients.len(), self.inputs);
+        assert_eq!(evaluations.len(), self.outputs);
+        // Correlate a_i ⋅ ρ^{-T(i)} with ρ^{T(i)}, using reversal to make it a convolution.
+        let mut buffer = vec![Fr::ZERO; self.plan.size()];
+        for ((b, a), c) in buffer[..self.inputs]
+            .iter_mut()
+            .rev()
+            .zip(coefficients)
+            .zip(&self.chirp)
+        {
+            *b = a * c;
+        }
+        self.plan.ntt(&mut buffer);
+        buffer
+            .iter_mut()
+            .zip(&self.kernel)
+            .for_each(|(b, k)| *b *= k);
+        self.plan.intt(&mut buffer);
+        for ((e, b), c) in evaluations
+            .iter_mut()
+            .zip(&buffer[self.inputs - 1..])
+            .zip(&self.chirp)
+        {
+            *e = b * c;
+        }
+    }
+}
+
+/// Evaluates the polynomial on the points $c ⋅ ρ^k$ for all `k < evaluations.len()`.
+pub fn chirp_z(coefficients: &[Fr], offset: Fr, ratio: Fr, evaluations: &mut [Fr]) {
+    if coefficients.is_empty() || evaluations.is_empty() {
+        evaluations.fill(Fr::ZERO);
+        return;
+    }
+    // P(X) -> P(c ⋅ X)
+    let mut offset_i = Fr::ONE;
+    let coefficients: Vec<Fr> = coefficients
+        .iter()
+        .map(|a| {
+            let a = *a * offset_i;
+            offset_i *= offset;
+            a
+        })
+        .collect();
+    ChirpZPlan::new(coefficients.len(), evaluations.len(), ratio)
+        .evaluate(&coefficients, evaluations);
+}
+
+/// Computes an NTT using a single-use plan.
+/// For repeated transforms of the same size create an [`NttPlan`] instead.
+pub fn ntt(values: &mut [Fr]) {
+    NttPlan::new(values.len()).ntt(values);
 }
 
 fn ntt_batch_inner(values: &mut [Fr], roots: &[Fr], size: usize) {
     debug_assert_eq!(values.len() % size, 0);
-    debug_assert!(roots.len() >= values.len());
-    debug_assert_eq!(roots.len() % values.len(), 0);
+    debug_assert_eq!(roots.len() % size, 0);
 
     match size {
         0 | 1 => {}
@@ -166,19 +292,39 @@ pub fn transpose<T: Copy>(matrix: &mut [T], rows: usize, cols: usize) {
     }
 }
 
+/// Computes an inverse NTT using a single-use plan.
 pub fn intt(values: &mut [Fr]) {
-    let s = Fr::from(values.len() as u64).inverse().unwrap();
-    values.iter_mut().for_each(|v| *v *= s);
-    values[1..].reverse();
-    ntt(values);
+    NttPlan::new(values.len()).intt(values);
+}
+
+/// Smallest supported NTT size that is at least `n`.
+pub fn smooth_size(n: usize) -> usize {
+    assert!(n <= 2415919104, "No NTT size larger than 2415919104");
+    [1, 3, 9]
+        .into_iter()
+        .map(|odd| odd * n.div_ceil(odd).next_power_of_two())
+        .filter(|size| 2415919104 % size == 0)
+        .min()
+        .unwrap()
 }
 
-// Compute a root of unity of the given order.
-fn root(order: usize) -> Option<Fr> {
-    if 2415919104 % order == 0 {
+/// Compute a root of unity of the given order.
+pub fn root(order: usize) -> Option<Fr> {
+    if order == 0 {
+        None
+    } else if 2415919104 % order == 0 {
         Some(OMEGA_2415919104.pow([(2415919104 / order) as u64]))
     } else {
-        None
+        // Compute (p - 1) / order using long division.
+        let mut exponent = Fr::MODULUS.0;
+        exponent[0] -= 1;
+        let mut remainder = 0_u128;
+        for limb in exponent.iter_mut().rev() {
+            let value = (remainder << 64) | *limb as u128;
+            *limb = (value / order as u128) as u64;
+            remainder = value % order as u128;
+        }
+        (remainder == 0).then(|| GENERATOR.pow(exponent))
     }
 }
 
@@ -193,20 +339,16 @@ fn sqrt_factor(n: usize) -> usize {
     }
 }
 
-fn lcm(a: usize, b: usize) -> usize {
-    a * b / gcd(a, b)
-}
-
-fn gcd(mut a: usize, mut b: usize) -> usize {
-    while b != 0 {
-        (a, b) = (b, a % b);
-    }
-    a
-}
-
 #[cfg(test)]
 mod test {
-    use {super::*, ark_ff::FftField, std::array};
+    use {
+        super::*,
+        ark_ff::FftField,
+        rand::{Rng, SeedableRng},
+        rand_chacha::ChaCha20Rng,
+        rayon::{iter::ParallelIterator, slice::ParallelSliceMut},
+        std::array,
+    };
 
     // O(n^2) Reference implementation
     pub fn ntt_ref(values: &mut [Fr]) {
@@ -253,11 +395,17 @@ mod test {
         assert_eq!(root(4).unwrap(), OMEGA_4_1);
         assert_eq!(root(8).unwrap(), OMEGA_8_1);
         assert_eq!(root(8).unwrap().pow([3]), OMEGA_8_3);
+        assert_eq!(root(13 * 29).unwrap().pow([13 * 29]), Fr::ONE);
+        assert_ne!(root(13 * 29).unwrap().pow([13]), Fr::ONE);
+        assert_ne!(root(13 * 29).unwrap().pow([29]), Fr::ONE);
+        assert_eq!(root(5), None);
     }
 
     #[test]
     fn test_ntt_ref() {
-        for size in [1, 2, 3, 4, 8, 12, 16, 32, 64, 128, 256, 512, 768, 1024] {
+        for size in [
+            1, 2, 3, 4, 8, 12, 13, 16, 26, 29, 32, 64, 128, 256, 377, 512, 768, 1024,
+        ] {
             dbg!(size);
             let mut values: Vec<Fr> = (0..size).map(|i| Fr::from(i as u64)).collect();
             let mut expected = values.clone();
@@ -275,4 +423,49 @@ mod test {
         intt(&mut values);
         assert_eq!(values, expected);
     }
+
+    #[test]
+    fn test_ntt_intt_bluestein() {
+        let mut values: Vec<Fr> = (0..13 * 29).map(|i| Fr::from(i as u64)).collect();
+        let expected = values.clone();
+        ntt(&mut values);
+        intt(&mut values);
+        assert_eq!(values, expected);
+    }
+
+    #[test]
+    fn test_chirp_z() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for (inputs, outputs) in [(0, 3), (1, 1), (5, 7), (10, 3), (100, 1000)] {
+            let coefficients: Vec<Fr> = (0..inputs).map(|_| rng.gen()).collect();
+            let (offset, ratio): (Fr, Fr) = (rng.gen(), rng.gen());
+            let mut evaluations = vec![Fr::ZERO; outputs];
+            chirp_z(&coefficients, offset, ratio, &mut evaluations);
+            for (k, e) in evaluations.iter().enumerate() {
+                let x = offset * ratio.pow([k as u64]);
+                let expected = coefficients
+                    .iter()
+                    .rev()
+                    .fold(Fr::ZERO, |acc, c| acc * x + c);
+                assert_eq!(*e, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_shared() {
+        let plan = NttPlan::new(96);
+        let mut values: Vec<Fr> = (0..96 * 64).map(|i| Fr::from(i as u64)).collect();
+        let mut expected = values.clone();
+        expected.chunks_exact_mut(96).for_each(ntt_ref);
+        values
+            .par_chunks_mut(96 * 4)
+            .for_each(|values| plan.ntt(values));
+        assert_eq!(values, expected);
+        plan.intt(&mut values);
+        assert!(values
+            .iter()
+            .enumerate()
+            .all(|(i, v)| *v == Fr::from(i as u64)));
+    }
 }
@@ -5,9 +5,8 @@ use {
 };
 
 pub fn commit(scalars: &[Fr]) -> MerkleTree {
-    let mut buffer = scalars.to_vec();
     let mut codeword = vec![Fr::ZERO; 4 * scalars.len()];
-    encode(&mut buffer, &mut codeword);
+    encode(scalars, &mut codeword);
 
     MerkleTree::new(codeword)
 }
@@ -1,7 +1,7 @@
 pub mod pedersen;
 
 use {
-    crate::{Prover, Verifier},
+    crate::transcript::{DomainSeparator, DuplexSponge, Prover, Verifier},
     ark_bn254::{Fr, G1Affine, G1Projective},
     ark_ec::VariableBaseMSM,
     ark_ff::Zero,
@@ -30,14 +30,24 @@ impl HyraxCommiter {
         }
     }
 
-    pub fn commit(&self, rng: &mut impl Rng, transcript: &mut Prover, f: &[Fr]) -> Vec<Fr> {
+    /// Domain separator binding transcripts to the row length.
+    pub fn domain_separator(&self) -> DomainSeparator {
+        DomainSeparator::new("hyrax", 1, &[Fr::from(self.pedersen.size() as u64)])
+    }
+
+    pub fn commit(
+        &self,
+        rng: &mut impl Rng,
+        transcript: &mut Prover<impl DuplexSponge>,
+        f: &[Fr],
+    ) -> Vec<Fr> {
         self.pedersen.batch_commit(rng, transcript, f)
     }
 
     pub fn proof_contraction(
         &self,
         rng: &mut impl Rng,
-        transcript: &mut Prover,
+        transcript: &mut Prover<impl DuplexSponge>,
         f: (&[Fr], &[Fr]), // Secrets and values
         a: &[Fr],          // Values
         b: &[Fr],          // Values
@@ -63,7 +73,7 @@ impl HyraxCommiter {
 
     pub fn verify_contraction(
         &self,
-        transcript: &mut Verifier,
+        transcript: &mut Verifier<impl DuplexSponge>,
         commitments: &[G1Affine],
         a: &[Fr],
         b: &[Fr],
@@ -96,7 +106,7 @@ mod test {
         let c = compute_contraction(&f, &a, &b);
 
         // Prove
-        let mut transcript = Prover::new();
+        let mut transcript: Prover = Prover::with_domain(&hyrax.domain_separator());
         let s = hyrax.commit(&mut rng, &mut transcript, &f);
         let (sc, cc) = hyrax.pedersen.commit(&mut rng, &[c]);
         transcript.write_g1(cc);
@@ -105,7 +115,7 @@ mod test {
         dbg!(proof.len() * std::mem::size_of::<Fr>());
 
         // Verify
-        let mut transcript = Verifier::new(&proof);
+        let mut transcript: Verifier = Verifier::with_domain(&hyrax.domain_separator(), &proof);
         let cs = (0..rows).map(|_| transcript.read_g1()).collect::<Vec<_>>();
         let cc = transcript.read_g1();
         hyrax.verify_contraction(&mut transcript, &cs, &a, &b, cc);
@@ -1,5 +1,5 @@
 use {
-    crate::{Prover, Verifier},
+    crate::transcript::{DomainSeparator, DuplexSponge, Prover, Verifier},
     ark_bn254::{Fr, G1Affine, G1Projective},
     ark_ec::VariableBaseMSM,
     rand::{Rng, SeedableRng},
@@ -33,6 +33,16 @@ impl PedersenCommitter {
         }
     }
 
+    /// Number of values a commitment holds.
+    pub fn size(&self) -> usize {
+        self.generators.len() - 1
+    }
+
+    /// Domain separator binding transcripts to the vector size.
+    pub fn domain_separator(&self) -> DomainSeparator {
+        DomainSeparator::new("pedersen", 1, &[Fr::from(self.size() as u64)])
+    }
+
     /// Commit to a value using the Pedersen commitment scheme.
     /// Returns the prover secret and the commitment.
     /// IDEA: Prover never uses the G1Affine, so we may as well write it to transcript?
@@ -55,11 +65,11 @@ impl PedersenCommitter {
     pub fn batch_commit(
         &self,
         rng: &mut impl Rng,
-        transcript: &mut Prover,
+        transcript: &mut Prover<impl DuplexSponge>,
         values: &[Fr],
     ) -> Vec<Fr> {
         assert!(
-            values.len() % (self.generators.len() - 1) == 0,
+            values.len().is_multiple_of(self.generators.len() - 1),
             "Values not whole number of vectors."
         );
         // This uses Pipenger, but for Hyrax we could also do WNAF over the columns.
@@ -86,7 +96,13 @@ impl PedersenCommitter {
     // Prove that two values are equal.
     // Only the secrets are required.
     // **Warning** This does not verify the vector lentghs and they are implicitely zero padded.
-    pub fn prove_equal(&self, rng: &mut impl Rng, transcript: &mut Prover, a: Fr, b: Fr) {
+    pub fn prove_equal(
+        &self,
+        rng: &mut impl Rng,
+        transcript: &mut Prover<impl DuplexSponge>,
+        a: Fr,
+        b: Fr,
+    ) {
         let (s, c) = self.commit(rng, &[]);
         transcript.write_g1(c);
         let r = transcript.read();
@@ -96,7 +112,7 @@ impl PedersenCommitter {
 
     pub fn verify_equal(
         &self,
-        transcript: &mut Verifier,
+        transcript: &mut Verifier<impl DuplexSponge>,
         a: G1Affine,
         b: G1Affine,
     ) -> Result<(), Error> {
@@ -115,7 +131,7 @@ impl PedersenCommitter {
     pub fn prove_product(
         &self,
         rng: &mut impl Rng,
-        transcript: &mut Prover,
+        transcript: &mut Prover<impl DuplexSponge>,
         a: (Fr, G1Affine, Fr),
         b: (Fr, Fr),
         c: Fr,
@@ -138,7 +154,7 @@ impl PedersenCommitter {
 
     pub fn verify_product(
         &self,
-        transcript: &mut Verifier,
+        transcript: &mut Verifier<impl DuplexSponge>,
         ca: G1Affine,
         cb: G1Affine,
         cc: G1Affine,
@@ -170,7 +186,7 @@ impl PedersenCommitter {
     pub fn prove_dot_product(
         &self,
         rng: &mut impl Rng,
-        transcript: &mut Prover,
+        transcript: &mut Prover<impl DuplexSponge>,
         a: (Fr, &[Fr]),
         b: &[Fr],
         c: Fr,
@@ -192,7 +208,7 @@ impl PedersenCommitter {
     // Verify that c = a . b.
     pub fn verify_dot_product(
         &self,
-        transcript: &mut Verifier,
+        transcript: &mut Verifier<impl DuplexSponge>,
         a: G1Affine,
         b: &[Fr],
         c: G1Affine,
@@ -244,7 +260,7 @@ mod test {
         let a = (0..size).map(|_| rng.gen()).collect::<Vec<Fr>>();
 
         // Prove
-        let mut transcript = Prover::new();
+        let mut transcript: Prover = Prover::with_domain(&pedersen.domain_separator());
         let (sa, ca) = pedersen.commit(&mut rng, &a);
         transcript.write_g1(ca);
         let (sb, cb) = pedersen.commit(&mut rng, &a);
@@ -254,7 +270,7 @@ mod test {
         dbg!(proof.len() * std::mem::size_of::<Fr>());
 
         // Verify
-        let mut transcript = Verifier::new(&proof);
+        let mut transcript: Verifier = Verifier::with_domain(&pedersen.domain_separator(), &proof);
         let ca = transcript.read_g1();
         let cb = transcript.read_g1();
         pedersen.verify_equal(&mut transcript, ca, cb).unwrap();
@@ -269,7 +285,7 @@ mod test {
         let c = a * b;
 
         // Prove
-        let mut transcript = Prover::new();
+        let mut transcript: Prover = Prover::with_domain(&pedersen.domain_separator());
         let (sa, ca) = pedersen.commit(&mut rng, &[a]);
         let (sb, cb) = pedersen.commit(&mut rng, &[b]);
         let (sc, cc) = pedersen.commit(&mut rng, &[c]);
@@ -281,7 +297,7 @@ mod test {
         dbg!(proof.len() * std::mem::size_of::<Fr>());
 
         // Verify
-        let mut transcript = Verifier::new(&proof);
+        let mut transcript: Verifier = Verifier::with_domain(&pedersen.domain_separator(), &proof);
         let ca = transcript.read_g1();
         let cb = transcript.read_g1();
         let cc = transcript.read_g1();
@@ -300,7 +316,7 @@ mod test {
         let c = a.iter().zip(b.iter()).map(|(a, b)| a * b).sum();
 
         // Prove
-        let mut transcript = Prover::new();
+        let mut transcript: Prover = Prover::with_domain(&pedersen.domain_separator());
         let (sa, ca) = pedersen.commit(&mut rng, &a);
         transcript.write_g1(ca);
         let (sc, cc) = pedersen.commit(&mut rng, &[c]);
@@ -310,7 +326,7 @@ mod test {
         dbg!(proof.len() * std::mem::size_of::<Fr>());
 
         // Verify
-        let mut transcript = Verifier::new(&proof);
+        let mut transcript: Verifier = Verifier::with_domain(&pedersen.domain_separator(), &proof);
         let ca = transcript.read_g1();
         let cc = transcript.read_g1();
         pedersen
@@ -1,39 +1,37 @@
 use {
     crate::{
-        merkle_tree::MerkleTree,
-        ntt::{intt, ntt, transpose},
-        pcs::hyrax::compute_contraction,
-        poseidon::compress,
-        reed_solomon::encode,
-        transcript::Prover,
+        linear_code::LinearCode,
+        merkle_tree::{MerkleConfig, MerkleTree},
+        ntt::transpose,
+        poseidon::HashDomain,
+        reed_solomon::ReedSolomon,
+        transcript::{DomainSeparator, DuplexSponge, Prover},
     },
     ark_bn254::Fr,
-    ark_ff::{Field, PrimeField},
+    ark_ff::Field,
     std::iter::once,
 };
 
-pub struct LigeroCommitter {
+pub struct LigeroCommitter<C: LinearCode = ReedSolomon> {
     pub rows: usize,
     pub cols: usize,
-    pub code: usize,
+    pub code: C,
     pub queries: usize,
     pub combinations: usize,
 }
 
-pub struct LigeroCommitment<'a> {
-    comitter: &'a LigeroCommitter,
-    matrix: Vec<Fr>,
+pub struct LigeroCommitment<'a, C: LinearCode = ReedSolomon> {
+    comitter: &'a LigeroCommitter<C>,
+    /// Merkle tree over the columns of the encoded matrix.
     tree: MerkleTree,
 }
 
-impl LigeroCommitter {
+impl LigeroCommitter<ReedSolomon> {
     pub fn new(security_bits: f64, size: usize) -> Self {
         let expansion = 4;
 
-        let queries =
-            (security_bits / (1.0 - (1.0 + 1.0 / (expansion as f64)).log2())).ceil() as usize;
-
         // Pick matrix aspect to minimize proof size.
+        let queries = queries(security_bits, 1.0 - 1.0 / expansion as f64);
         let rows = divisor_close_to(
             size,
             (2.0 * (size as f64) / (queries as f64)).sqrt() as usize,
@@ -41,54 +39,82 @@ impl LigeroCommitter {
         let cols = size / rows;
         assert_eq!(rows * cols, size, "Invalid size.");
 
-        let code = expansion * cols;
-        let combinations =
-            1 + ((security_bits - 1.0) / (253.6 - (code as f64).log2())).floor() as usize;
+        let committer = Self::with_code(
+            security_bits,
+            rows,
+            ReedSolomon::new(cols, expansion * cols),
+        );
         assert_eq!(
-            combinations, 1,
+            committer.combinations, 1,
             "Multiple combinations should not happen at this field size."
         );
+        committer
+    }
+}
 
+impl<C: LinearCode> LigeroCommitter<C> {
+    /// Committer for a `rows` × `code.message_length()` matrix using an arbitrary code.
+    pub fn with_code(security_bits: f64, rows: usize, code: C) -> Self {
+        let queries = queries(security_bits, code.relative_distance());
+        let combinations = combinations(security_bits, code.codeword_length());
         Self {
             rows,
-            cols,
+            cols: code.message_length(),
             code,
             queries,
             combinations,
         }
     }
 
-    pub fn commit(&self, transcript: &mut Prover, f: &[Fr]) -> LigeroCommitment {
+    /// Domain separator binding transcripts to these parameters.
+    pub fn domain_separator(&self) -> DomainSeparator {
+        let parameters = [
+            self.rows,
+            self.cols,
+            self.code.codeword_length(),
+            self.queries,
+            self.combinations,
+        ];
+        DomainSeparator::new("ligero", 1, &parameters.map(|p| Fr::from(p as u64)))
+    }
+
+    pub fn commit(
+        &self,
+        transcript: &mut Prover<impl DuplexSponge>,
+        f: &[Fr],
+    ) -> LigeroCommitment<'_, C> {
         assert_eq!(f.len(), self.rows * self.cols);
 
         // Encode values
-        let mut buffer = vec![Fr::ZERO; self.cols];
-        let mut encoded = vec![Fr::ZERO; self.rows * self.code];
+        let code = self.code.codeword_length();
+        let mut encoded = vec![Fr::ZERO; self.rows * code];
         for (f, e) in f
             .chunks_exact(self.cols)
-            .zip(encoded.chunks_exact_mut(self.code))
+            .zip(encoded.chunks_exact_mut(code))
         {
-            buffer.copy_from_slice(f);
-            encode(&mut buffer, e);
+            self.code.encode_systematic(f, e);
         }
 
-        // Hash columns and construct merkle tree.
-        transpose(&mut encoded, self.rows, self.code);
-        let tree = MerkleTree::new(encoded.chunks_exact(self.rows).map(compress).collect());
+        // Construct merkle tree with columns as leaves.
+        transpose(&mut encoded, self.rows, code);
+        let config = MerkleConfig {
+            leaf_domain: HashDomain::Column,
+            ..MerkleConfig::default()
+        };
+        let tree = MerkleTree::with_public_config(config, self.rows, encoded);
 
         transcript.write(tree.root());
         LigeroCommitment {
             comitter: self,
-            matrix: encoded,
             tree,
         }
     }
 }
 
-impl LigeroCommitment<'_> {
+impl<C: LinearCode> LigeroCommitment<'_, C> {
     pub fn prove_contraction(
         &self,
-        transcript: &mut Prover,
+        transcript: &mut Prover<impl DuplexSponge>,
         a: &[Fr], // Values
         b: &[Fr], // Values
     ) {
@@ -104,41 +130,160 @@ impl LigeroCommitment<'_> {
         }
 
         // Compute the linear combinations of the rows and send to verifier.
+        // The matrix is stored column-major and the message appears in the systematic columns.
         for a in once(a).chain(r.chunks_exact(self.comitter.rows)) {
-            let mut combination = vec![Fr::ZERO; self.comitter.cols];
-            // TODO: Matrix doesn't have right transposition, plus code is not necessarily systematized.
-            for (a, row) in a.iter().zip(self.matrix.chunks_exact(self.comitter.code)) {
-                let row = &row[..self.comitter.cols];
-                for (c, row) in combination.iter_mut().zip(row.iter()) {
-                    *c += a * row;
-                }
+            for i in 0..self.comitter.cols {
+                let column = self.tree.leaf(self.comitter.code.systematic_index(i));
+                transcript.write(a.iter().zip(column).map(|(a, c)| a * c).sum());
             }
-            combination.into_iter().for_each(|c| transcript.write(c));
         }
 
         // Decommit columns
-        let indices: Vec<usize> = (0..self.comitter.queries)
-            .map(|_| {
-                let index = transcript.read();
-                index.into_bigint().as_ref()[0] as usize % self.comitter.cols
-            })
-            .collect();
-        for index in indices {
-            let column = self
-                .matrix
-                .chunks_exact(self.comitter.rows)
-                .nth(index)
-                .unwrap();
-            for value in column {
-                transcript.reveal(*value);
-            }
-            self.tree.reveal(transcript, index);
-        }
+        let size = self.comitter.code.codeword_length();
+        let indices = transcript.read_indices(size, self.comitter.queries.min(size));
+        self.tree.reveal_many(transcript, &indices);
     }
 }
 
-fn divisor_close_to(n: usize, target: usize) -> usize {
+/// Number of column queries for the proximity test with a code of relative
+/// distance δ. A combination of rows that is δ/3 far from the code passes each
+/// query with probability at most 1 - δ/3, as in Ligero and Brakedown. When
+/// this exceeds the codeword length all columns are opened.
+fn queries(security_bits: f64, relative_distance: f64) -> usize {
+    (security_bits / -(1.0 - relative_distance / 3.0).log2()).ceil() as usize
+}
+
+/// Number of random row combinations for the proximity test.
+fn combinations(security_bits: f64, codeword_length: usize) -> usize {
+    1 + ((security_bits - 1.0) / (253.6 - (codeword_length as f64).log2())).floor() as usize
+}
+
+fn divisor_close_to(_n: usize, target: usize) -> usize {
     // Assume n is a power of two.
     // TODO: More generic method.
     1 << target.ilog2()
 }
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            expander_code::ExpanderCode, merkle_tree::verify_many, pcs::hyrax::compute_contraction,
+            transcript::Verifier,
+        },
+        rand::{Rng, SeedableRng},
+        rand_chacha::ChaCha20Rng,
+    };
+
+    #[test]
+    fn test_contraction_consistent() {
+        check_contraction(&LigeroCommitter::new(128.0, 1 << 12));
+    }
+
+    #[test]
+    fn test_contraction_expander() {
+        // The sound number of queries exceeds the codeword length at this size,
+        // use fewer (not sound) so that columns are sampled.
+        let committer = LigeroCommitter {
+            queries: 64,
+            ..LigeroCommitter::with_code(128.0, 16, ExpanderCode::new(256))
+        };
+        assert!(committer.queries < committer.code.codeword_length());
+        check_contraction(&committer);
+    }
+
+    #[test]
+    fn test_corrupted_column() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let committer = LigeroCommitter::new(128.0, 1 << 12);
+        let (rows, cols) = (committer.rows, committer.cols);
+        let f = (0..rows * cols).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let a = (0..rows).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let b = (0..cols).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let c = compute_contraction(&f, &a, &b);
+
+        // Corrupt the first row in the non-systematic columns past the first
+        // `cols`. The combinations only depend on the systematic columns and are
+        // still consistent, so only queries over the full codeword catch this.
+        let mut transcript: Prover = Prover::with_domain(&committer.domain_separator());
+        // Only the tree of this commitment is used, its transcript is discarded.
+        let commitment = committer.commit(&mut Prover::new(), &f);
+        let systematic: Vec<_> = (0..cols)
+            .map(|i| committer.code.systematic_index(i))
+            .collect();
+        let mut columns = commitment.tree.leaves().to_vec();
+        for (i, column) in columns.chunks_exact_mut(rows).enumerate() {
+            if i >= cols && !systematic.contains(&i) {
+                column[0] += Fr::ONE;
+            }
+        }
+        let tree = MerkleTree::with_public_config(commitment.tree.config(), rows, columns);
+        transcript.write(tree.root());
+        let commitment = LigeroCommitment {
+            comitter: &committer,
+            tree,
+        };
+        transcript.write(c);
+        commitment.prove_contraction(&mut transcript, &a, &b);
+        let proof = transcript.finish();
+        assert!(!verify_contraction(&committer, &proof, &a, &b));
+    }
+
+    fn check_contraction<C: LinearCode>(committer: &LigeroCommitter<C>) {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let (rows, cols) = (committer.rows, committer.cols);
+        let f = (0..rows * cols).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let a = (0..rows).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let b = (0..cols).map(|_| rng.gen()).collect::<Vec<Fr>>();
+        let c = compute_contraction(&f, &a, &b);
+
+        // Prove
+        let mut transcript: Prover = Prover::with_domain(&committer.domain_separator());
+        let commitment = committer.commit(&mut transcript, &f);
+        transcript.write(c);
+        commitment.prove_contraction(&mut transcript, &a, &b);
+        let proof = transcript.finish();
+        assert!(verify_contraction(committer, &proof, &a, &b));
+    }
+
+    /// Check the combinations against the committed columns.
+    fn verify_contraction<C: LinearCode>(
+        committer: &LigeroCommitter<C>,
+        proof: &[Fr],
+        a: &[Fr],
+        b: &[Fr],
+    ) -> bool {
+        let (rows, cols) = (committer.rows, committer.cols);
+        let mut transcript: Verifier = Verifier::with_domain(&committer.domain_separator(), proof);
+        let root = transcript.read();
+        let c = transcript.read();
+        let r = (0..rows).map(|_| transcript.generate()).collect::<Vec<_>>();
+        let ua = (0..cols).map(|_| transcript.read()).collect::<Vec<_>>();
+        let ur = (0..cols).map(|_| transcript.read()).collect::<Vec<_>>();
+        if ua.iter().zip(b).map(|(u, b)| u * b).sum::<Fr>() != c {
+            return false;
+        }
+        let size = committer.code.codeword_length();
+        let mut ea = vec![Fr::ZERO; size];
+        let mut er = vec![Fr::ZERO; size];
+        committer.code.encode_systematic(&ua, &mut ea);
+        committer.code.encode_systematic(&ur, &mut er);
+        let indices = transcript.generate_indices(size, committer.queries.min(size));
+        let config: MerkleConfig = MerkleConfig {
+            leaf_domain: HashDomain::Column,
+            ..MerkleConfig::default()
+        };
+        let Ok(columns) = verify_many(&mut transcript, config, &[root], size, rows, &indices)
+        else {
+            return false;
+        };
+        indices
+            .iter()
+            .zip(columns.chunks_exact(rows))
+            .all(|(&index, column)| {
+                a.iter().zip(column).map(|(a, c)| a * c).sum::<Fr>() == ea[index]
+                    && r.iter().zip(column).map(|(r, c)| r * c).sum::<Fr>() == er[index]
+            })
+    }
+}
@@ -0,0 +1,398 @@
+//! Dense univariate polynomials over the BN254 scalar field.
+//! Fast algorithms use the NTT, so domains are restricted to the sizes it supports.
+use {
+    crate::ntt::{intt, ntt, smooth_size, NttPlan},
+    ark_bn254::Fr,
+    ark_ff::{Field, Zero},
+    std::ops::{Add, Mul, Neg, Sub},
+};
+
+/// Below this size the quadratic algorithms outperform the NTT based ones.
+const NTT_THRESHOLD: usize = 64;
+
+/// Polynomial in coefficient form, without trailing zero coefficients.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DensePolynomial {
+    coefficients: Vec<Fr>,
+}
+
+impl DensePolynomial {
+    pub fn new(mut coefficients: Vec<Fr>) -> Self {
+        while coefficients.last().is_some_and(Fr::is_zero) {
+            coefficients.pop();
+        }
+        Self { coefficients }
+    }
+
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    /// Computes $\prod_i (X - r_i)$.
+    pub fn from_roots(roots: &[Fr]) -> Self {
+        match roots {
+            [] => Self::new(vec![Fr::ONE]),
+            [root] => Self::new(vec![-*root, Fr::ONE]),
+            roots => {
+                let (a, b) = roots.split_at(roots.len() / 2);
+                &Self::from_roots(a) * &Self::from_roots(b)
+            }
+        }
+    }
+
+    /// Vanishing polynomial $X^n - c^n$ of the coset $c ⋅ ⟨ω_n⟩$.
+    pub fn vanishing(size: usize, offset: Fr) -> Self {
+        let mut coefficients = vec![Fr::ZERO; size + 1];
+        coefficients[0] = -offset.pow([size as u64]);
+        coefficients[size] = Fr::ONE;
+        Self::new(coefficients)
+    }
+
+    /// Interpolates evaluations on the coset $c ⋅ ⟨ω_n⟩$.
+    pub fn interpolate_coset(evaluations: &[Fr], offset: Fr) -> Self {
+        let mut coefficients = evaluations.to_vec();
+        intt(&mut coefficients);
+        // P(c ⋅ X) -> P(X)
+        let offset = offset.inverse().expect("Coset offset must be non-zero.");
+        let mut offset_i = Fr::ONE;
+        for c in coefficients.iter_mut() {
+            *c *= offset_i;
+            offset_i *= offset;
+        }
+        Self::new(coefficients)
+    }
+
+    /// Interpolates evaluations on distinct points using Lagrange interpolation.
+    // OPT: Quadratic, a subproduct tree would make this quasi-linear.
+    pub fn interpolate(points: &[Fr], values: &[Fr]) -> Self {
+        assert_eq!(points.len(), values.len());
+        let vanishing = Self::from_roots(points);
+        let mut result = vec![Fr::ZERO; points.len()];
+        for (&x, &y) in points.iter().zip(values) {
+            // Z(X) / (X - x) using synthetic division.
+            let mut basis = vec![Fr::ZERO; points.len()];
+            let mut carry = Fr::ZERO;
+            for (b, z) in basis.iter_mut().zip(&vanishing.coefficients[1..]).rev() {
+                carry = carry * x + z;
+                *b = carry;
+            }
+            let basis = Self::new(basis);
+            let weight = y * basis
+                .evaluate(x)
+                .inverse()
+                .expect("Points must be distinct.");
+            for (r, b) in result.iter_mut().zip(&basis.coefficients) {
+                *r += weight * b;
+            }
+        }
+        Self::new(result)
+    }
+
+    pub fn coefficients(&self) -> &[Fr] {
+        &self.coefficients
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// Degree of the polynomial, the zero polynomial has degree zero.
+    pub fn degree(&self) -> usize {
+        self.coefficients.len().saturating_sub(1)
+    }
+
+    /// Evaluates the polynomial at a point using Horner's method.
+    pub fn evaluate(&self, x: Fr) -> Fr {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(Fr::ZERO, |acc, c| acc * x + c)
+    }
+
+    /// Evaluates the polynomial at many points.
+    /// Uses recursive remaindering by the vanishing polynomials of the point sets.
+    // OPT: Reuse the subproduct tree instead of recomputing it at every level.
+    pub fn evaluate_many(&self, points: &[Fr]) -> Vec<Fr> {
+        if points.len() <= NTT_THRESHOLD || self.coefficients.len() <= NTT_THRESHOLD {
+            return points.iter().map(|&x| self.evaluate(x)).collect();
+        }
+        let (a, b) = points.split_at(points.len() / 2);
+        let (_, ra) = self.div_rem(&Self::from_roots(a));
+        let (_, rb) = self.div_rem(&Self::from_roots(b));
+        let mut result = ra.evaluate_many(a);
+        result.extend(rb.evaluate_many(b));
+        result
+    }
+
+    /// Evaluates the polynomial on the coset $c ⋅ ⟨ω_n⟩$.
+    pub fn evaluate_coset(&self, size: usize, offset: Fr) -> Vec<Fr> {
+        // P(X) -> P(c ⋅ X) mod X^n - 1
+        let mut values = vec![Fr::ZERO; size];
+        let mut offset_i = Fr::ONE;
+        for (i, c) in self.coefficients.iter().enumerate() {
+            values[i % size] += offset_i * c;
+            offset_i *= offset;
+        }
+        ntt(&mut values);
+        values
+    }
+
+    /// Computes quotient and remainder of division by `divisor`.
+    pub fn div_rem(&self, divisor: &Self) -> (Self, Self) {
+        assert!(!divisor.is_zero(), "Division by zero polynomial.");
+        if self.coefficients.len() < divisor.coefficients.len() {
+            return (Self::zero(), self.clone());
+        }
+        let quotient_len = self.coefficients.len() - divisor.coefficients.len() + 1;
+        if quotient_len.min(divisor.coefficients.len()) <= NTT_THRESHOLD {
+            return self.div_rem_schoolbook(divisor);
+        }
+
+        // Reversed polynomials turn division into a power series inversion.
+        let reversed =
+            |p: &[Fr], len: usize| -> Vec<Fr> { p.iter().rev().take(len).copied().collect() };
+        let inverse = inverse_series(&reversed(&divisor.coefficients, quotient_len), quotient_len);
+        let mut quotient = mul_coefficients(
+            &reversed(&self.coefficients, quotient_len),
+            &inverse.coefficients,
+        );
+        quotient.truncate(quotient_len);
+        quotient.resize(quotient_len, Fr::ZERO);
+        quotient.reverse();
+        let quotient = Self::new(quotient);
+        let remainder = self - &(&quotient * divisor);
+        (quotient, remainder)
+    }
+
+    /// Computes quotient and remainder of division by the vanishing polynomial
+    /// $X^n - c^n$ of the coset $c ⋅ ⟨ω_n⟩$.
+    pub fn divide_by_vanishing(&self, size: usize, offset: Fr) -> (Self, Self) {
+        assert!(size > 0);
+        if self.coefficients.len() <= size {
+            return (Self::zero(), self.clone());
+        }
+        // X^n = c^n mod Z(X), so fold the coefficients from the top down.
+        let offset = offset.pow([size as u64]);
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![Fr::ZERO; remainder.len() - size];
+        for i in (size..remainder.len()).rev() {
+            let q = remainder[i];
+            quotient[i - size] = q;
+            remainder[i - size] += offset * q;
+        }
+        remainder.truncate(size);
+        (Self::new(quotient), Self::new(remainder))
+    }
+
+    fn div_rem_schoolbook(&self, divisor: &Self) -> (Self, Self) {
+        let inverse = divisor.coefficients.last().unwrap().inverse().unwrap();
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![Fr::ZERO; remainder.len() - divisor.coefficients.len() + 1];
+        for i in (0..quotient.len()).rev() {
+            let q = remainder[i + divisor.coefficients.len() - 1] * inverse;
+            quotient[i] = q;
+            for (r, d) in remainder[i..].iter_mut().zip(divisor.coefficients.iter()) {
+                *r -= q * d;
+            }
+        }
+        remainder.truncate(divisor.coefficients.len() - 1);
+        (Self::new(quotient), Self::new(remainder))
+    }
+}
+
+/// Computes $f^{-1} \mod X^n$ using Newton iteration.
+fn inverse_series(f: &[Fr], n: usize) -> DensePolynomial {
+    let mut inverse = vec![f[0].inverse().expect("Constant term must be non-zero.")];
+    let mut len = 1;
+    while len < n {
+        len = (2 * len).min(n);
+        // g' = g ⋅ (2 - f ⋅ g) mod X^len
+        let mut fg = mul_coefficients(&f[..len.min(f.len())], &inverse);
+        fg.truncate(len);
+        fg.iter_mut().for_each(|c| *c = -*c);
+        fg[0] += Fr::from(2);
+        inverse = mul_coefficients(&inverse, &fg);
+        inverse.truncate(len);
+    }
+    DensePolynomial::new(inverse)
+}
+
+/// Multiplies two coefficient vectors, the result is not normalized.
+fn mul_coefficients(a: &[Fr], b: &[Fr]) -> Vec<Fr> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let len = a.len() + b.len() - 1;
+    if a.len().min(b.len()) <= NTT_THRESHOLD {
+        let mut result = vec![Fr::ZERO; len];
+        for (i, a) in a.iter().enumerate() {
+            for (r, b) in result[i..].iter_mut().zip(b) {
+                *r += a * b;
+            }
+        }
+        return result;
+    }
+    let size = smooth_size(len);
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    a.resize(size, Fr::ZERO);
+    b.resize(size, Fr::ZERO);
+    let plan = NttPlan::new(size);
+    plan.ntt(&mut a);
+    plan.ntt(&mut b);
+    a.iter_mut().zip(b.iter()).for_each(|(a, b)| *a *= b);
+    plan.intt(&mut a);
+    a.truncate(len);
+    a
+}
+
+impl Add for &DensePolynomial {
+    type Output = DensePolynomial;
+
+    fn add(self, rhs: Self) -> DensePolynomial {
+        let (long, short) = if self.coefficients.len() >= rhs.coefficients.len() {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
+        let mut coefficients = long.coefficients.clone();
+        coefficients
+            .iter_mut()
+            .zip(short.coefficients.iter())
+            .for_each(|(a, b)| *a += b);
+        DensePolynomial::new(coefficients)
+    }
+}
+
+impl Sub for &DensePolynomial {
+    type Output = DensePolynomial;
+
+    fn sub(self, rhs: Self) -> DensePolynomial {
+        self + &-rhs
+    }
+}
+
+impl Neg for &DensePolynomial {
+    type Output = DensePolynomial;
+
+    fn neg(self) -> DensePolynomial {
+        DensePolynomial {
+            coefficients: self.coefficients.iter().map(|c| -*c).collect(),
+        }
+    }
+}
+
+impl Mul for &DensePolynomial {
+    type Output = DensePolynomial;
+
+    fn mul(self, rhs: Self) -> DensePolynomial {
+        DensePolynomial::new(mul_coefficients(&self.coefficients, &rhs.coefficients))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        rand::{Rng, SeedableRng},
+        rand_chacha::ChaCha20Rng,
+    };
+
+    fn random(rng: &mut impl Rng, len: usize) -> DensePolynomial {
+        DensePolynomial::new((0..len).map(|_| rng.gen()).collect())
+    }
+
+    #[test]
+    fn test_mul() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for (n, m) in [
+            (0, 5),
+            (1, 1),
+            (10, 20),
+            (100, 200),
+            (300, 65),
+            (1000, 1000),
+        ] {
+            let a = random(&mut rng, n);
+            let b = random(&mut rng, m);
+            let c = &a * &b;
+            let x: Fr = rng.gen();
+            assert_eq!(c.evaluate(x), a.evaluate(x) * b.evaluate(x));
+        }
+    }
+
+    #[test]
+    fn test_div_rem() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for (n, m) in [
+            (5, 10),
+            (10, 3),
+            (100, 20),
+            (500, 200),
+            (1000, 100),
+            (2000, 1000),
+        ] {
+            let a = random(&mut rng, n);
+            let b = random(&mut rng, m);
+            let (q, r) = a.div_rem(&b);
+            assert!(r.coefficients.len() < b.coefficients.len());
+            assert_eq!(&(&q * &b) + &r, a);
+        }
+    }
+
+    #[test]
+    fn test_divide_by_vanishing() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let offset = Fr::from(5);
+        for (n, size) in [(10, 16), (100, 16), (1000, 96)] {
+            let a = random(&mut rng, n);
+            let (q, r) = a.divide_by_vanishing(size, offset);
+            assert_eq!(
+                (q.clone(), r.clone()),
+                a.div_rem(&DensePolynomial::vanishing(size, offset))
+            );
+            assert_eq!(&(&q * &DensePolynomial::vanishing(size, offset)) + &r, a);
+        }
+    }
+
+    #[test]
+    fn test_coset() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let offset = Fr::from(5);
+        for (n, size) in [(10, 12), (64, 64), (100, 64), (200, 768)] {
+            let a = random(&mut rng, n);
+            let evaluations = a.evaluate_coset(size, offset);
+            let root = crate::ntt::root(size).unwrap();
+            for (i, e) in evaluations.iter().enumerate() {
+                assert_eq!(*e, a.evaluate(offset * root.pow([i as u64])));
+            }
+            if n <= size {
+                assert_eq!(DensePolynomial::interpolate_coset(&evaluations, offset), a);
+            }
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let a = random(&mut rng, 50);
+        let points: Vec<Fr> = (0..60).map(|_| rng.gen()).collect();
+        let values = a.evaluate_many(&points);
+        assert_eq!(DensePolynomial::interpolate(&points, &values), a);
+        assert_eq!(
+            DensePolynomial::interpolate(&[], &[]),
+            DensePolynomial::zero()
+        );
+    }
+
+    #[test]
+    fn test_evaluate_many() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let a = random(&mut rng, 500);
+        let points: Vec<Fr> = (0..300).map(|_| rng.gen()).collect();
+        let expected: Vec<Fr> = points.iter().map(|&x| a.evaluate(x)).collect();
+        assert_eq!(a.evaluate_many(&points), expected);
+        let roots = DensePolynomial::from_roots(&points);
+        assert!(roots.evaluate_many(&points).iter().all(Fr::is_zero));
+    }
+}
@@ -0,0 +1,183 @@
+//! The original Poseidon hash as implemented by circomlib, for widths 2 to 13.
+//!
+//! circomlib also supports widths up to 17, these are not exposed as no
+//! reference vectors were available to check them against.
+//!
+//! Round constants and the Cauchy MDS matrix are generated with the Grain LFSR
+//! as in `generate_parameters_grain.sage` from the Poseidon reference, which is
+//! how circomlib's `poseidon_constants` were produced.
+//!
+//! # References
+//!
+//! * Lorenzo Grassi, Dmitry Khovratovich, Christian Rechberger, Arnab Roy, Markus Schofnegger (2019).
+//!   Poseidon: A New Hash Function for Zero-Knowledge Proof Systems.
+//!   <https://eprint.iacr.org/2019/458>
+//! * <https://github.com/iden3/circomlib/blob/master/circuits/poseidon.circom>
+//! * <https://github.com/iden3/circomlibjs/blob/main/src/poseidon_reference.js>
+use {
+    super::grain::Grain,
+    ark_bn254::Fr,
+    ark_ff::{Field, PrimeField},
+    std::sync::OnceLock,
+};
+
+pub const FULL_ROUNDS: usize = 8;
+
+/// Partial rounds for widths 2 to 13.
+pub const PARTIAL_ROUNDS: [usize; 12] = [56, 57, 56, 60, 60, 63, 64, 63, 60, 66, 60, 65];
+
+static INSTANCES: [OnceLock<Poseidon>; 12] = [const { OnceLock::new() }; 12];
+
+/// A Poseidon instance with S-box $x^5$.
+pub struct Poseidon {
+    pub width: usize,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    /// Round constants, `width` per round.
+    pub constants: Vec<Fr>,
+    /// MDS matrix, row-major.
+    pub mds: Vec<Fr>,
+}
+
+impl Poseidon {
+    /// Instance with parameters from the Grain LFSR. The first Cauchy matrix
+    /// with distinct indices is used, as circomlib did. The reference script
+    /// now also rejects matrices with invariant subspace trails, which is not
+    /// implemented here, so only the circomlib instances are exposed.
+    fn new(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        assert!(full_rounds.is_multiple_of(2));
+        let mut grain = Grain::new(
+            Fr::MODULUS_BIT_SIZE as usize,
+            width,
+            full_rounds,
+            partial_rounds,
+        );
+        let constants = (0..(full_rounds + partial_rounds) * width)
+            .map(|_| grain.next_field())
+            .collect();
+        let mds = loop {
+            let xy: Vec<Fr> = (0..2 * width).map(|_| grain.next_field_reduced()).collect();
+            let (x, y) = xy.split_at(width);
+            let distinct = xy
+                .iter()
+                .enumerate()
+                .all(|(i, a)| xy[..i].iter().all(|b| a != b));
+            let mds: Option<Vec<Fr>> = x
+                .iter()
+                .flat_map(|x| y.iter().map(move |y| (*x + y).inverse()))
+                .collect();
+            if let (true, Some(mds)) = (distinct, mds) {
+                break mds;
+            }
+        };
+        Self {
+            width,
+            full_rounds,
+            partial_rounds,
+            constants,
+            mds,
+        }
+    }
+
+    pub fn permute(&self, state: &mut [Fr]) {
+        assert_eq!(state.len(), self.width);
+        let half = self.full_rounds / 2;
+        let mut product = vec![Fr::ZERO; self.width];
+        for (round, rc) in self.constants.chunks_exact(self.width).enumerate() {
+            state.iter_mut().zip(rc).for_each(|(x, rc)| *x += rc);
+            if round < half || round >= half + self.partial_rounds {
+                state
+                    .iter_mut()
+                    .for_each(|x| *x *= x.square().square_in_place());
+            } else {
+                state[0] *= state[0].square().square_in_place();
+            }
+            for (p, row) in product.iter_mut().zip(self.mds.chunks_exact(self.width)) {
+                *p = row.iter().zip(state.iter()).map(|(m, x)| *m * x).sum();
+            }
+            state.copy_from_slice(&product);
+        }
+    }
+}
+
+/// The circomlib instance of the given width.
+pub fn instance(width: usize) -> &'static Poseidon {
+    assert!((2..=13).contains(&width), "Unsupported Poseidon width.");
+    INSTANCES[width - 2]
+        .get_or_init(|| Poseidon::new(width, FULL_ROUNDS, PARTIAL_ROUNDS[width - 2]))
+}
+
+/// Same as circomlib's `Poseidon(n)` template for 1 to 12 inputs.
+pub fn hash(inputs: &[Fr]) -> Fr {
+    let mut state = vec![Fr::ZERO; inputs.len() + 1];
+    state[1..].copy_from_slice(inputs);
+    instance(state.len()).permute(&mut state);
+    state[0]
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, ark_ff::MontFp};
+
+    fn hash_u64(inputs: &[u64]) -> Fr {
+        hash(&inputs.iter().copied().map(Fr::from).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_vectors() {
+        // From the circomlibjs tests.
+        assert_eq!(
+            hash_u64(&[1, 2]),
+            MontFp!("7853200120776062878684798364095072458815029376092732009249414926327459813530")
+        );
+        assert_eq!(
+            hash_u64(&[1, 2, 3, 4]),
+            MontFp!(
+                "18821383157269793795438455681495246036402687001665670618754263018637548127333"
+            )
+        );
+        assert_eq!(
+            hash_u64(&[1, 2, 0, 0, 0]),
+            MontFp!("1018317224307729531995786483840663576608797660851238720571059489595066344487")
+        );
+        assert_eq!(
+            hash_u64(&[1]),
+            MontFp!(
+                "18586133768512220936620570745912940619677854269274689475585506675881198879027"
+            )
+        );
+    }
+
+    #[test]
+    fn test_vectors_ones() {
+        // Circom hashes of 1 to 12 ones, from the light-poseidon 0.2.0 tests
+        // (`tests/bn254_fq_x5.rs`).
+        let expected: [Fr; 12] = [
+            MontFp!(
+                "18586133768512220936620570745912940619677854269274689475585506675881198879027"
+            ),
+            MontFp!("217234377348884654691879377518794323857294947151490278790710809376325639809"),
+            MontFp!("1243904711429961858774220647610724273798918457991486031567244100767259239747"),
+            MontFp!("3697322215802076228208066929658130683674438861307808350825760082336385039729"),
+            MontFp!("7336984428078952600237169304321914358474313324708186973411450536267727944123"),
+            MontFp!(
+                "19202028150024867662780481379838409946689630430681355990725140707642055614070"
+            ),
+            MontFp!(
+                "15587463278332567001059474568613449501521353873733009860467391356348271308984"
+            ),
+            MontFp!(
+                "10625958540850080708582130653289575059817043739407999759992126519343968888964"
+            ),
+            MontFp!("6586682917253118491448025671540200955685514868114810493222463743094482488391"),
+            MontFp!(
+                "21154062150014026117492338523992511721617667294911573944785740169656578526614"
+            ),
+            MontFp!("200076502165720972416075356324368328696787933591141519854940792926668635035"),
+            MontFp!("9147049232282027787779787872110122248895227518511153992880076767178851520446"),
+        ];
+        for (inputs, expected) in (1..).zip(expected) {
+            assert_eq!(hash(&vec![Fr::ONE; inputs]), expected);
+        }
+    }
+}
@@ -1,9 +1,57 @@
-//! From https://github.com/HorizenLabs/poseidon2/blob/bb476b9ca38198cf5092487283c8b8c5d4317c4e/plain_implementations/src/poseidon2/poseidon2_instance_bn256.rs
+//! Round constants for the Poseidon2 instances.
+//!
+//! The tables are from https://github.com/HorizenLabs/poseidon2/blob/bb476b9ca38198cf5092487283c8b8c5d4317c4e/plain_implementations/src/poseidon2/poseidon2_instance_bn256.rs
 //! Also generated with `poseidon2_rust_params.sage` from that repo.
-use {ark_bn254::Fr, ark_ff::MontFp};
+//! Without the `precomputed-constants` feature they are generated on first use
+//! instead, saving ~24kB of binary size.
+use ark_bn254::Fr;
+#[cfg(feature = "precomputed-constants")]
+use ark_ff::MontFp;
 
-// TODO: These are pure random and can also be generated, saving ~16kB of size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoundConstants<F, const WIDTH: usize> {
+    /// Constants for the initial full rounds.
+    pub initial: Vec<[F; WIDTH]>,
+    /// Constants for the first state element in the partial rounds.
+    pub partial: Vec<F>,
+    /// Constants for the terminal full rounds.
+    pub terminal: Vec<[F; WIDTH]>,
+}
 
+impl<F: Copy, const WIDTH: usize, const FULL: usize, const PARTIAL: usize>
+    From<([[F; WIDTH]; FULL], [F; PARTIAL], [[F; WIDTH]; FULL])> for RoundConstants<F, WIDTH>
+{
+    fn from(table: ([[F; WIDTH]; FULL], [F; PARTIAL], [[F; WIDTH]; FULL])) -> Self {
+        Self {
+            initial: table.0.to_vec(),
+            partial: table.1.to_vec(),
+            terminal: table.2.to_vec(),
+        }
+    }
+}
+
+pub fn rc3() -> RoundConstants<Fr, 3> {
+    #[cfg(feature = "precomputed-constants")]
+    return RC3.into();
+    #[cfg(not(feature = "precomputed-constants"))]
+    return super::grain::round_constants(8, 56);
+}
+
+pub fn rc16() -> RoundConstants<Fr, 16> {
+    #[cfg(feature = "precomputed-constants")]
+    return RC16.into();
+    #[cfg(not(feature = "precomputed-constants"))]
+    return super::grain::round_constants(8, 57);
+}
+
+pub fn rc24() -> RoundConstants<Fr, 24> {
+    #[cfg(feature = "precomputed-constants")]
+    return RC24.into();
+    #[cfg(not(feature = "precomputed-constants"))]
+    return super::grain::round_constants(8, 57);
+}
+
+#[cfg(feature = "precomputed-constants")]
 pub static RC3: ([[Fr; 3]; 4], [Fr; 56], [[Fr; 3]; 4]) = (
     [
         [
@@ -139,6 +187,7 @@ pub static RC3: ([[Fr; 3]; 4], [Fr; 56], [[Fr; 3]; 4]) = (
     ],
 );
 
+#[cfg(feature = "precomputed-constants")]
 pub static RC16: ([[Fr; 16]; 4], [Fr; 57], [[Fr; 16]; 4]) = (
     [
         [
@@ -488,3 +537,495 @@ pub static RC16: ([[Fr; 16]; 4], [Fr; 57], [[Fr; 16]; 4]) = (
         ],
     ],
 );
+
+// Width 24 with R_F = 8 and R_P = 57, generated with the same Grain LFSR as above.
+#[cfg(feature = "precomputed-constants")]
+pub static RC24: ([[Fr; 24]; 4], [Fr; 57], [[Fr; 24]; 4]) = (
+    [
+        [
+            MontFp!("2906941459715237703693315249735315959493359099150891459336992122015009295512"),
+            MontFp!("8470565272952660716214681620272070150586403116004715829655741125969244335519"),
+            MontFp!("8724598673693190102083693301672100633295663987036547498453609580223375507198"),
+            MontFp!(
+                "10767450212024301817298688464815820206307956950979239398150408829392630476079"
+            ),
+            MontFp!("7594679093721642453372324863747978029825097167892057831093379247626578307374"),
+            MontFp!(
+                "21442109983578388008981318201436343021958097881603521045044801789698239594788"
+            ),
+            MontFp!(
+                "18159640123456833273189873941174213610099428504252085160334120699876370935681"
+            ),
+            MontFp!("1330183234305713341228292852147773090533244581798737285435475912386431168086"),
+            MontFp!("7016775967716425790086222943725398714618182298805601689493851063479292569034"),
+            MontFp!("2793840554702877919785426946668048098147609718622644306555687889047807123795"),
+            MontFp!(
+                "18538419629684213238438587633308543503695156984507500533320731671415558047754"
+            ),
+            MontFp!("6364569375760875209280167542206652440942390202317535903423664147211763269150"),
+            MontFp!(
+                "16513405451797000053283486385774070066665972565635665956946584246067077551519"
+            ),
+            MontFp!("200048850753215890021281009204388786482890623196969543437988465404106526485"),
+            MontFp!("9302692335522241850421998396419475539766342955745594315829599624498388931831"),
+            MontFp!(
+                "10279012191761204408959944976797210194613804019780874880517788322808400625160"
+            ),
+            MontFp!(
+                "12748513078500780951532720130471330627556507336463939912431485762020933584008"
+            ),
+            MontFp!("6089876974392171603696963884626901888506548026973236708876199102602656076777"),
+            MontFp!(
+                "19092316860336980564792439712081989228033862331712420270234571628536664374749"
+            ),
+            MontFp!("7292669833383023447661933682407762064879016454016295956688183206850924030415"),
+            MontFp!(
+                "19920270879425145513917900105421176702957941537006962853191104987345188067931"
+            ),
+            MontFp!(
+                "11004024425177224293267348867487924324767054017457516774315561491201813939139"
+            ),
+            MontFp!("3826995307362049552051855794611898271613625901663383491073177264611521085799"),
+            MontFp!("48868906771434851085510618225765978087951613295705243102325081964608567008"),
+        ],
+        [
+            MontFp!("2072155842617985534844316898505749326345188652675921011604228553758166681418"),
+            MontFp!(
+                "12694320720525075777718113419973780366856507530661476211601785536216746314340"
+            ),
+            MontFp!("3877859957778762679332270179953342546321858177202947940592630628635918269607"),
+            MontFp!("3638397603053363520834227614924120580915848980765531312240419747896610491765"),
+            MontFp!("3913501694857412304706134877529725312240898704585030324327249001054885801303"),
+            MontFp!("5205833835164569744749154870180619545565684621991036694878630689983624744775"),
+            MontFp!("5727312812576694189586032877516674198112150418158979543896107305246077533788"),
+            MontFp!(
+                "20995441788656237132629730078307180388703757932400726110578024521098868170689"
+            ),
+            MontFp!(
+                "17935740380249801865164190921174168493276096825284031845982857825359142804195"
+            ),
+            MontFp!(
+                "11922075421617137377699476197849115120334130602717042533918142014515785639556"
+            ),
+            MontFp!(
+                "14076000501121351627517599468611930310287604198266283827575485946169495816380"
+            ),
+            MontFp!(
+                "13732561600580411166687275191611336369352526654384673888676363749638993514453"
+            ),
+            MontFp!(
+                "14663291151444734019086346856785368592331205256854938491394662584865044911344"
+            ),
+            MontFp!(
+                "10878955338337459201932788375437982302312909737540966526875071781731734338329"
+            ),
+            MontFp!("4612432643740904209906300060294907563171117446313911566167357188373392768542"),
+            MontFp!("6233901844489175632382029551756358822271977158448207072185093601051094263164"),
+            MontFp!("3195776342621060648851149072215207951067842029379449812526083031825514868867"),
+            MontFp!("5206905192522674370467897873815553989133958990354189643071196245787705323402"),
+            MontFp!(
+                "13180160154887124726358587999959586386879274504844375558582281789562314545541"
+            ),
+            MontFp!(
+                "14226931141476919717522373252318879099658057947687323192645374018007737840738"
+            ),
+            MontFp!("90085363044188912202953536150114978396199904747849320497374124233575723545"),
+            MontFp!(
+                "19510696993989913682161716551831546817687403060810176954919847665289720979965"
+            ),
+            MontFp!(
+                "21588396407656842178576465076532304240296258010506537224543529391364920929246"
+            ),
+            MontFp!(
+                "10725944466368518282744297864558499911896478766890702671920420862739616583448"
+            ),
+        ],
+        [
+            MontFp!(
+                "10253647258014055494385656773672258262507906659599053492900205966122092320335"
+            ),
+            MontFp!(
+                "10579594679476396329424024196744355398568497945920535262619855966063559252824"
+            ),
+            MontFp!(
+                "11774068327829053623336719948124859040601204649106071419276736634578009212301"
+            ),
+            MontFp!("8311930456803645237624747878651052487165526216208253306785027911443323742431"),
+            MontFp!(
+                "10928701801660326511344904309905344463673524254834676391264484670982555215984"
+            ),
+            MontFp!(
+                "12159348009598909694947425131073466738846374216437144048974412409802444953855"
+            ),
+            MontFp!(
+                "10908243582007813861905921959814580825280790430477335702003098628875064652917"
+            ),
+            MontFp!("3193863534001730191765731262267943140400740041011457359427824486721977429267"),
+            MontFp!(
+                "16862041809785571265849283227167199332570024655737343276362561612840445382916"
+            ),
+            MontFp!(
+                "12909275134950186069136362805784704558525666733992583797423424195521210422600"
+            ),
+            MontFp!("8623685819587670650560615603245140646103596923538731883231231329501224085292"),
+            MontFp!(
+                "21204371473329969641345483797546554545961706260441195180804413341465375129437"
+            ),
+            MontFp!(
+                "18016782569757867110531510052419762342546004267456608065122718815604667303699"
+            ),
+            MontFp!(
+                "16676491063751603515910796751904463386925036107349011267513459754505559023518"
+            ),
+            MontFp!("9587227944009546613809726313160067524883119998416636430395390427863563044322"),
+            MontFp!("5518688774583125899967504265567666732989033280534943627675045383871753001528"),
+            MontFp!("8794000104123255545865092983730269952405097369232364541250948618879376025360"),
+            MontFp!("1345422352535780583841542665190619269642687853678619341932589310081820870672"),
+            MontFp!("146314907447991581742739583255929323985256012537316155755674849936752605297"),
+            MontFp!("7753291915359737300001775205339634348671388447926536429236302166707944107208"),
+            MontFp!(
+                "15708377965873285187194396463257652613251140410286286571755658715187697460854"
+            ),
+            MontFp!(
+                "19366698664210307841613220679020163100622319606669208795935402535061945407073"
+            ),
+            MontFp!(
+                "13161958618491049469662114031450301469982728690637919638327772116019986553478"
+            ),
+            MontFp!("8858932363482294668351117695419984716545216447477791357030305178248858370586"),
+        ],
+        [
+            MontFp!(
+                "15949997097498965126198098274715872886744189963274644053995583629969388539808"
+            ),
+            MontFp!(
+                "16769786631722598896571556803081197955965263413779439189425099932632458824633"
+            ),
+            MontFp!("4291066856047224737938147410300339691252218690929444271884798372390277543315"),
+            MontFp!("2984928694884424771018099206297978890680226444748604149024396508397036324392"),
+            MontFp!("1348117595322332653582626731930279039370895223794857736268059074427396252219"),
+            MontFp!(
+                "18782960369362682468972478965568901587285345010184611075496569447083277970903"
+            ),
+            MontFp!(
+                "15145144149146131205772196848046293226024916259430984609507017899191064402740"
+            ),
+            MontFp!("1858849064632265450144551494109938936796625217799853094179528020121647522963"),
+            MontFp!(
+                "14871918762146923169454976805077473470036823546966340262910011112921300235781"
+            ),
+            MontFp!(
+                "20040942713249875248202243571356592042950891271563055943064334805903728720640"
+            ),
+            MontFp!(
+                "10777155522028368689574267929347039327490251930810767717320783551792877861791"
+            ),
+            MontFp!(
+                "14161433622410024079637711978449043204044574266127179921444026737512471118363"
+            ),
+            MontFp!("7694654854454292336912767441265029037665316114743302160849078254723400909603"),
+            MontFp!("8337710846306686530417794978563354375270292716250359405932443521104790233005"),
+            MontFp!(
+                "17306999327722137503250669575977747250421665536660801843471986518239721647124"
+            ),
+            MontFp!("1667640437439199905523657830117998977101568112154168491019062406605533260150"),
+            MontFp!("4287058154447655738283366747718464692761989245449837262630094484444334685088"),
+            MontFp!("3034409027513464891307681031647134306982505857408513680771522395124962756434"),
+            MontFp!("1572894492504067845799204445280891783336983581950061853604531915566521834462"),
+            MontFp!("9432782625790748459388632637550313598006768973480105187535236347505545592071"),
+            MontFp!(
+                "10606083203119111991173319153884364456053796856269492758561287228327315289895"
+            ),
+            MontFp!("3839122299425872363494631414682845941658890131312745552033609517066203445974"),
+            MontFp!("5265767579918912497200322465607070156430292852471521949089575419212665853743"),
+            MontFp!(
+                "14543224326150134466944720989639417848214782465999753862689826086498419657768"
+            ),
+        ],
+    ],
+    [
+        MontFp!("9546846230154966336495900536916400736745858948864425109594981814036594695961"),
+        MontFp!("15977604601396123455889085318093478281847491796556874259042883448758577564680"),
+        MontFp!("6658104993144413293425463157088083132475213300555250053306265473069369815411"),
+        MontFp!("442708041058907740057425500078763077991467600681757367003924686720056904428"),
+        MontFp!("17486892427729399682639066579204398957363216679486833382163310357920843817450"),
+        MontFp!("1111818067326216885440168375408781756115564156872605838081671190598770836543"),
+        MontFp!("18424373920499955952331948898090676521911350167327357170317830750357010544897"),
+        MontFp!("14111154358768223657636548338797161380668561632909062393807382802260602040322"),
+        MontFp!("21195660018709499790283164749196794332360138202866736796518586272643186360705"),
+        MontFp!("3192488660518153340750896012731594433143695922628176862698387898871956117235"),
+        MontFp!("13513341272597133729456365217819268579665369463273493438989865635883032263263"),
+        MontFp!("14417476977063054505666990266949040910892550533610386365435105906113808451298"),
+        MontFp!("8984873798818377067249699910799124886267939101173535708333480846696596885185"),
+        MontFp!("8120073494420979168946383264911244595850888928838482428988270077763305865317"),
+        MontFp!("2141568798183189838932116925191044642409662235620832049327856649828917486511"),
+        MontFp!("9649926043399796453609288212271209628882272809338437899282971008811860589221"),
+        MontFp!("6618024557909518619648076399332747832328775109159312994480652366089656770158"),
+        MontFp!("13540001492819992690242022958866553762957318176691332009624080943311270918255"),
+        MontFp!("18084899261538477783885089527457703453357131732028446607076846615267302163313"),
+        MontFp!("4622966421287914755189225674295998870565830350856700221048097403882075419401"),
+        MontFp!("18647330998664921242090106346929254888368733370404120332116451337488568330501"),
+        MontFp!("18292856242557500522994435947684503627125042097690939620598759889268663941438"),
+        MontFp!("6619045281387898013706538123573131451403863979852784669624445904159810609903"),
+        MontFp!("2882236983283311961692469639643111612297030652412868530251554020282994847257"),
+        MontFp!("3817274556174060475730567422879666563148613055735456971936449738274141760875"),
+        MontFp!("18212392621880035640172140439520831721833579312840351641606021351924387991948"),
+        MontFp!("13839752897334396486600667957543845777250340194804996035396485205431730319424"),
+        MontFp!("3298480526208186403463479956071476063309981749068644377042587214655369212"),
+        MontFp!("10009711397247510685278864335565467210399341900379847966571659720081065942330"),
+        MontFp!("8786075016438373516383958547858059103099698223186289561435691613930650708176"),
+        MontFp!("12076292184527635235277023991152933094666408261026549199596913332748754786625"),
+        MontFp!("8517560628412454913957078877599809604522073135320719558850773169398214891101"),
+        MontFp!("12284239513544688241601032662590559729986671982580595117792813148996323193299"),
+        MontFp!("13538369470897361474788788314835324031519744152595953544187684325527909399099"),
+        MontFp!("19021800696841356557765223866030661168436823881348888668617656323181522389274"),
+        MontFp!("14614108720502277648931202256618984605697517048007604595924044937277287731398"),
+        MontFp!("6517111122200281471914945582193560405866079405461508662362699007724528467313"),
+        MontFp!("357128724801758611930284518920063759857217701311983151659543357946763501750"),
+        MontFp!("19080646629150579355984729308430934145501264314877551992170191680533590989601"),
+        MontFp!("14229645423963977900668301863131422473480058282684486185242770051186236604264"),
+        MontFp!("13477926122023315207443321143792822091960480924477014823889116151170329849386"),
+        MontFp!("21542966031593006248875808248623287974734248149929603771082977905943751650266"),
+        MontFp!("3816426750769109325974836615030958887521135047105103873222264241989413652155"),
+        MontFp!("18710210467326031240699570663713997128739242132060902395549839760301944211263"),
+        MontFp!("12125263324373444420209640289568795715930814651654604326231683869111847556736"),
+        MontFp!("4021648331264872710342808463879128265332652832313488646967028925838569785093"),
+        MontFp!("3910526474643989996327727386714837051136683912562008802622064470535084416840"),
+        MontFp!("16703287115555343210644404014619692248480139950793484905949000344105452877622"),
+        MontFp!("12175478148374026493459496231565555278697128182257804551555772221005804367907"),
+        MontFp!("4825923202709075635207907481892912364850390531948959824635129970159142429382"),
+        MontFp!("15192388881294185225573722225431278706345326004948027012850674644232449028426"),
+        MontFp!("19654211696613240119785231862273432157110944953250354344348042661691973019688"),
+        MontFp!("148394540970217695689215515691712031802723236394482207628943644821420334403"),
+        MontFp!("2973743364610368350817874648980312724360942079746576030148922044781831678192"),
+        MontFp!("6893324572300099489145387093856356319297699550490729530138163683068051440756"),
+        MontFp!("10628458692662616499817062376622368704954526285945973641495983779575601675265"),
+        MontFp!("10072162063793958550989218928970856939339780505276451234297728925206583131190"),
+    ],
+    [
+        [
+            MontFp!(
+                "13413769498110190478084854973884628827310285955842471307164671153864285900374"
+            ),
+            MontFp!(
+                "19855339043043451011008979103502544682878435294918096817904134493929621925395"
+            ),
+            MontFp!(
+                "18564183438658983151663554074093752127120680175606674914094352122681954362223"
+            ),
+            MontFp!("9846762434395667153695932440391058935184764799363604217955728774297857337381"),
+            MontFp!(
+                "21149010153376043567720843559374760572384125237773114669064601870437241333738"
+            ),
+            MontFp!(
+                "21474628545907465777080037027072960896735362347350344931580594473418181095489"
+            ),
+            MontFp!(
+                "12053337835852582303790465800393079269570433919558118600148618290875378953335"
+            ),
+            MontFp!(
+                "13843608088789277972591742068366953138832337020846501500613347278535214861021"
+            ),
+            MontFp!(
+                "13246131014938043232462393114018605282730765342563872538236555163726379483858"
+            ),
+            MontFp!("4271538757853593982930846758924616852617678021487627806111876628600170024328"),
+            MontFp!(
+                "15384346190175625885974458478486976895377490258993348347066269173553671716982"
+            ),
+            MontFp!(
+                "13552081662025657645983982762579007596193790581931314441636055749811267585112"
+            ),
+            MontFp!(
+                "16273145201431446072236569358354784733649998083173168260006422017369938400104"
+            ),
+            MontFp!("2368628652875402466992804180218343946462806554109682430854604197311943418399"),
+            MontFp!(
+                "14992096888125247220791382638085934863232359481653406132116061902978628499476"
+            ),
+            MontFp!(
+                "21636728892056660494983936553120782960307193257108027927851600075735878163995"
+            ),
+            MontFp!("8482067329714169894097587559876985628689725103488676063620033698605043644381"),
+            MontFp!("9884720000717782516737552302247213854015312167728062681020888863824280767309"),
+            MontFp!("7361163012329999269545666139878696591460078309215846953809422232069742136554"),
+            MontFp!(
+                "18770545107816766455646402387908008885123447129912598277180793946822039590412"
+            ),
+            MontFp!(
+                "13143365764650560852281277893451401187913569676956511617209622050815384938216"
+            ),
+            MontFp!(
+                "12263183366552070332520115507196075929948260789445514188681345496867098795758"
+            ),
+            MontFp!("7959614772751246922620594314185554540123830214805168601695317750218066681019"),
+            MontFp!("2568919532637132573223548721830166484687697333319855415540308167118843931976"),
+        ],
+        [
+            MontFp!("8558770800563377444431299561919533683709346794368337351211922307006257303954"),
+            MontFp!("9591525032731873343208768257466445043092713377726869449038587253832689504898"),
+            MontFp!(
+                "10293915343003162437854381747188315921777877214638537449779788088919168950556"
+            ),
+            MontFp!("6338228251765237163019921479208886364879027656696575859007063160670341700989"),
+            MontFp!("4827070039501448886713596259620207937752101681299680769322424848131547159856"),
+            MontFp!("6739505979464923879930084720234745835108486270942578891709209704364684872804"),
+            MontFp!("7363818432725991840212978483734305293722149745558777266556687962333115403942"),
+            MontFp!("7092403997745801468960601786712177517891666986326553343769787461036342868919"),
+            MontFp!("8581577286010240768850013020895208147127699912086740242359224690153985921324"),
+            MontFp!("9561303921680570745289503883950377910109032250675177569921436971385334497505"),
+            MontFp!("5555183875637865434297097886702325576547987560524916702405513469676375267222"),
+            MontFp!(
+                "12935711117337965196388555825805669464941981076998358163726758884257250316126"
+            ),
+            MontFp!("4907115014828972643417459846666058081354442171889784278191039523005641467513"),
+            MontFp!(
+                "16062404427665104581098709754495445662059483164858838464795524131965321590562"
+            ),
+            MontFp!(
+                "14011222166660881335161967203801767104469989868845622064034462026698890893222"
+            ),
+            MontFp!(
+                "10469268694166975768184107678935485572633891184431316528361197168953426288387"
+            ),
+            MontFp!("6258951082715220081633452967874365162528231998377949192870638156788444229936"),
+            MontFp!(
+                "15520895059486994279919085906642819272172293859428298231555099808717766468097"
+            ),
+            MontFp!(
+                "19215722009461328848873784287452521504704238141797720860579291394206226843237"
+            ),
+            MontFp!("3458813284827882273174857255603039864425338208894540125580991551045402491295"),
+            MontFp!(
+                "10286777160843377062939668374047114681209983556572565818474740816059060178768"
+            ),
+            MontFp!(
+                "17081775889144725939692725810195063352948801524262530902681414539623052533153"
+            ),
+            MontFp!(
+                "16991405698299633148631332783617895826573885261227708482163906636039821357461"
+            ),
+            MontFp!(
+                "18734190199206029221519265327036377794580627297198023325642282075848418488670"
+            ),
+        ],
+        [
+            MontFp!("454400627789381518396005942313002526004357463452108940521654974650659536238"),
+            MontFp!(
+                "17141386259666470168822946033766583142819599072362884179662873042535124423840"
+            ),
+            MontFp!("6798953461563405587129908259872475327211551401681316569167696119188942700965"),
+            MontFp!(
+                "20107576769402623333842663589726089882918143650114489025840162228751504910710"
+            ),
+            MontFp!("8455530442330362337989433974502560250596915036564427480460309770398971935369"),
+            MontFp!(
+                "16422522512539553123549697641541335591835601897357464978543666410897976851790"
+            ),
+            MontFp!(
+                "13555387283443609284750231303693220923696698987598813809387931297208156040627"
+            ),
+            MontFp!(
+                "13028535137566354215106822770245031300095101827324158678626472452598475580984"
+            ),
+            MontFp!(
+                "19987972645192867888481233828881908712949114697228474052783054862937358779127"
+            ),
+            MontFp!("5048281263216664535801519214606385843855092649673104485405162484981860456027"),
+            MontFp!("6263968085982933550325448281976139654527007832340633584243322503698564589754"),
+            MontFp!("6999471599207558481552345341838071291249806737448392233215704311761946200828"),
+            MontFp!("4083717015101961220823256322477989258915801622924337385779056229779341086098"),
+            MontFp!("7990709539713721278354503621792925297171343983340220020784340538292344183439"),
+            MontFp!("4160921749874956286849313388113721470036989782181654109787193178299484789342"),
+            MontFp!(
+                "18586702274018604369096561562054483786280782122938126160300908669958224164040"
+            ),
+            MontFp!(
+                "10413945363108940412129436199232582371191369689661786399783512100050054862098"
+            ),
+            MontFp!(
+                "21488634366825126107274142530242894325126183972814936011240652042375210546364"
+            ),
+            MontFp!("3756367910028882258523487684958514400914335847865911221636811819751857448534"),
+            MontFp!(
+                "21487637056208507513257691577200595380781587762612985266036627394927993092083"
+            ),
+            MontFp!(
+                "11119717957156488230729959371056788295471030946671399345742249107426753665805"
+            ),
+            MontFp!(
+                "10045806472595631870169503221340177347125248162526739827270047784129902386436"
+            ),
+            MontFp!(
+                "14218496716806437381261534962337825945226205215763582056297751601684521287014"
+            ),
+            MontFp!("9055354597971980001965082876067034849240127663704793881950377976942010398587"),
+        ],
+        [
+            MontFp!(
+                "13052826555848735540631411538357645432547195082441164440468556635565181301608"
+            ),
+            MontFp!("5406430544007307261211320428352999457877689642454334211102373770148966916027"),
+            MontFp!(
+                "18086376696062975345711509820518414553368091594939639523079294190989018308733"
+            ),
+            MontFp!(
+                "11257279345235369180756582170612723386122333527848127689115283664809362027504"
+            ),
+            MontFp!("4302242165709103869929403441212582279720949079701089350655296849225972341098"),
+            MontFp!(
+                "21097801159718046561439184972115294555602718964836824264145471606325332969770"
+            ),
+            MontFp!(
+                "14631356453469164855460959310311272097091223252586646425784532619756621023031"
+            ),
+            MontFp!(
+                "12314572696083018584061734956098413472404337791218225974087015891250393729797"
+            ),
+            MontFp!(
+                "21186380356709644970609093462369837914942602699208769432257572056897378312195"
+            ),
+            MontFp!("1237388691821465649489103025767507482205120614454508405099907463368477290466"),
+            MontFp!(
+                "16497194547651041044221746236588780048446044947257741138490189735569482262719"
+            ),
+            MontFp!(
+                "13374113349464470502599814135865956961084692199804429788294845131658461798168"
+            ),
+            MontFp!(
+                "15353978156326804077490684687992234057120289313679421179488790009883010018012"
+            ),
+            MontFp!("8338077503909208668353420665616701108219796995626838696776173981108286734937"),
+            MontFp!(
+                "16730988541952888907390729822421050661687603130485040057649979207984367163405"
+            ),
+            MontFp!(
+                "19065605651948574285877009621806283382375571886963317271573762818175088681958"
+            ),
+            MontFp!(
+                "13817123610034474599630830074580415104404214814244652546751899055280091151132"
+            ),
+            MontFp!(
+                "14152759604516575546150196861340573740420423753950294469130930975285868087726"
+            ),
+            MontFp!(
+                "10066404151622189096341171634726337279138060053360947738046195529540828971842"
+            ),
+            MontFp!(
+                "11319922200677939344343209186521743788092796941910389339287185534059171939840"
+            ),
+            MontFp!(
+                "18680372784528048997654932198289487419657574758143698811115332477433565946571"
+            ),
+            MontFp!(
+                "19469154808483502079221084291797359167077515799912358812760033643470309576185"
+            ),
+            MontFp!(
+                "12557069236428697067167589662709109118555891743804447963634814556481355143871"
+            ),
+            MontFp!(
+                "12425631225491097954769772304330647495157175635793923054932709231547145692023"
+            ),
+        ],
+    ],
+);
@@ -0,0 +1,118 @@
+//! Grain LFSR generation of Poseidon2 round constants, following the reference
+//! `poseidon2_rust_params.sage` from HorizenLabs.
+use {
+    super::constants::RoundConstants,
+    ark_ff::{BigInteger, PrimeField},
+    std::array,
+};
+
+/// The 80-bit self-shrinking Grain LFSR.
+pub struct Grain {
+    // Bit `i` is the `i`-th bit of the reference sequence.
+    state: u128,
+}
+
+impl Grain {
+    /// Initialize for a prime field instance with S-box $x^α$.
+    pub fn new(field_bits: usize, width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let fields = [
+            (1, 2), // Prime field
+            (0, 4), // S-box x^α
+            (field_bits, 12),
+            (width, 12),
+            (full_rounds, 10),
+            (partial_rounds, 10),
+            ((1 << 30) - 1, 30),
+        ];
+        let mut state = 0_u128;
+        let mut position = 0;
+        for (value, bits) in fields {
+            assert!(value < 1 << bits, "Parameter out of range.");
+            for bit in (0..bits).rev() {
+                state |= (((value >> bit) & 1) as u128) << position;
+                position += 1;
+            }
+        }
+        let mut grain = Self { state };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let s = self.state;
+        let bit = (s >> 62 ^ s >> 51 ^ s >> 38 ^ s >> 23 ^ s >> 13 ^ s) & 1;
+        self.state = s >> 1 | bit << 79;
+        bit == 1
+    }
+
+    /// Next output bit, the second bit of each pair whose first bit is set.
+    pub fn next_bit(&mut self) -> bool {
+        while !self.step() {
+            self.step();
+        }
+        self.step()
+    }
+
+    /// Uniform field element by rejection sampling big-endian bit strings.
+    pub fn next_field<F: PrimeField>(&mut self) -> F {
+        let mut bits = vec![false; F::MODULUS_BIT_SIZE as usize];
+        loop {
+            bits.iter_mut().for_each(|bit| *bit = self.next_bit());
+            if let Some(value) = F::from_bigint(F::BigInt::from_bits_be(&bits)) {
+                return value;
+            }
+        }
+    }
+
+    /// Field element from a big-endian bit string reduced modulo the field size.
+    /// The reference scripts sample MDS matrices this way.
+    pub fn next_field_reduced<F: PrimeField>(&mut self) -> F {
+        let bits: Vec<bool> = (0..F::MODULUS_BIT_SIZE).map(|_| self.next_bit()).collect();
+        F::from_le_bytes_mod_order(&F::BigInt::from_bits_be(&bits).to_bytes_le())
+    }
+}
+
+/// Round constants for a Poseidon2 instance over `F`. Partial rounds have a
+/// single constant for the first state element.
+pub fn round_constants<F: PrimeField, const WIDTH: usize>(
+    full_rounds: usize,
+    partial_rounds: usize,
+) -> RoundConstants<F, WIDTH> {
+    assert!(full_rounds.is_multiple_of(2));
+    let mut grain = Grain::new(
+        F::MODULUS_BIT_SIZE as usize,
+        WIDTH,
+        full_rounds,
+        partial_rounds,
+    );
+    let initial = (0..full_rounds / 2)
+        .map(|_| array::from_fn(|_| grain.next_field()))
+        .collect();
+    let partial = (0..partial_rounds).map(|_| grain.next_field()).collect();
+    let terminal = (0..full_rounds / 2)
+        .map(|_| array::from_fn(|_| grain.next_field()))
+        .collect();
+    RoundConstants {
+        initial,
+        partial,
+        terminal,
+    }
+}
+
+#[cfg(all(test, feature = "precomputed-constants"))]
+mod test {
+    use {
+        super::*,
+        crate::poseidon::constants::{RC16, RC24, RC3},
+        ark_bn254::Fr,
+    };
+
+    #[test]
+    fn test_generated_constants() {
+        assert_eq!(round_constants::<Fr, 3>(8, 56), RC3.into());
+        assert_eq!(round_constants::<Fr, 16>(8, 57), RC16.into());
+        assert_eq!(round_constants::<Fr, 24>(8, 57), RC24.into());
+    }
+}
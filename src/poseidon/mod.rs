@@ -20,23 +20,67 @@
 // https://extgit.iaik.tugraz.at/krypto/zkfriendlyhashzoo/-/blob/master/plain_impls/src/poseidon2/poseidon2_instance_bn256.rs?ref_type=heads
 // https://eprint.iacr.org/2024/310.pdf
 
+pub mod circom;
 mod constants;
+pub mod grain;
+mod poseidon2;
+mod redundant;
 
+pub use self::{
+    constants::RoundConstants,
+    poseidon2::{Poseidon2, LANES},
+};
 use {
-    self::constants::{RC16, RC3},
     ark_bn254::Fr,
     ark_ff::Field,
-    std::sync::atomic::{AtomicU32, Ordering},
+    std::sync::{
+        atomic::{AtomicU32, Ordering},
+        LazyLock,
+    },
 };
 
+pub static POSEIDON2_2: LazyLock<Poseidon2<2>> = LazyLock::new(|| Poseidon2::new(8, 56, [1, 2]));
+pub static POSEIDON2_3: LazyLock<Poseidon2<3>> =
+    LazyLock::new(|| Poseidon2::with_constants(constants::rc3(), [1, 1, 2]));
+pub static POSEIDON2_4: LazyLock<Poseidon2<4>> =
+    LazyLock::new(|| Poseidon2::new(8, 56, [0, 2, 4, 6]));
+pub static POSEIDON2_8: LazyLock<Poseidon2<8>> =
+    LazyLock::new(|| Poseidon2::new(8, 57, [0, 1, 3, 4, 5, 6, 7, 8]));
+pub static POSEIDON2_12: LazyLock<Poseidon2<12>> =
+    LazyLock::new(|| Poseidon2::new(8, 57, [0, 1, 2, 3, 4, 5, 7, 8, 9, 10, 11, 12]));
+pub static POSEIDON2_16: LazyLock<Poseidon2<16>> = LazyLock::new(|| {
+    Poseidon2::with_constants(
+        constants::rc16(),
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 14, 16, 17],
+    )
+});
+pub static POSEIDON2_24: LazyLock<Poseidon2<24>> = LazyLock::new(|| {
+    Poseidon2::with_constants(constants::rc24(), std::array::from_fn(|i| i as u64))
+});
+
+pub static POSEIDON2_28: LazyLock<Poseidon2<28>> = LazyLock::new(|| {
+    Poseidon2::new(
+        8,
+        59,
+        [
+            0, 1, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+            26, 27, 28, 29,
+        ],
+    )
+});
+
 pub static COUNT_3: AtomicU32 = AtomicU32::new(0);
 pub static COUNT_16: AtomicU32 = AtomicU32::new(0);
+pub static COUNT_24: AtomicU32 = AtomicU32::new(0);
 
 /// Compress arbitrary length inputs.
+///
+/// Inputs are zero padded, so this is not injective over input lengths. Use
+/// [`compress_with_domain`] where that matters.
 // Compute 16-arry Merkle tree over input.
 // Layers are zero padded.
 // Compresses nodes using truncated width-16 Poseidon2.
-// TODO: We can go to 24-ary tree with 24 width Poseidon2.
+// TODO: We can go to 24-ary tree with `permute_24`.
 pub fn compress(input: &[Fr]) -> Fr {
     if input.len() <= 16 {
         let mut state = [Fr::ZERO; 16];
@@ -48,73 +92,126 @@ pub fn compress(input: &[Fr]) -> Fr {
         let mut state = [Fr::ZERO; 16];
         // Compute the largest power of 16 < input.len();
         let chunk = 1 << (4 * ((input.len() - 1).ilog2() / 4));
-        for (s, chunk) in state.iter_mut().zip(input.chunks(chunk)) {
-            *s = compress(chunk);
+        if chunk == 16 {
+            // Children are single permutations, compute them as a batch.
+            let mut leaves = [[Fr::ZERO; 16]; 16];
+            let count = input.len().div_ceil(16);
+            for (leaf, chunk) in leaves.iter_mut().zip(input.chunks(16)) {
+                leaf[..chunk.len()].copy_from_slice(chunk);
+            }
+            permute_16_many(&mut leaves[..count]);
+            for (s, leaf) in state.iter_mut().zip(&leaves[..count]) {
+                *s = leaf[0];
+            }
+        } else {
+            for (s, chunk) in state.iter_mut().zip(input.chunks(chunk)) {
+                *s = compress(chunk);
+            }
         }
         permute_16(&mut state);
         state[0]
     }
 }
 
-pub fn permute_3(state: &mut [Fr; 3]) {
-    COUNT_3.fetch_add(1, Ordering::Relaxed);
-    mat_full_3(state);
-    for rc in RC3.0 {
-        state.iter_mut().zip(rc).for_each(|(x, rc)| *x += rc);
-        state
-            .iter_mut()
-            .for_each(|x| *x *= x.square().square_in_place());
-        mat_full_3(state);
-    }
-    for rc in RC3.1 {
-        state[0] += rc;
-        state[0] *= state[0].square().square_in_place();
+/// Node types for [`compress_with_domain`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HashDomain {
+    /// Merkle leaf vectors.
+    Leaf = 1,
+    /// Internal Merkle nodes.
+    Node = 2,
+    /// Columns of a committed matrix.
+    Column = 3,
+    /// A Merkle leaf hash combined with its hiding salt.
+    Salt = 4,
+    /// Transcript domain separators.
+    Separator = 5,
+    /// Transcript labels.
+    Label = 6,
+}
 
-        // TODO: Why is this one more operations than the MDS matrix?
-        let sum: Fr = state.iter().sum();
-        state[2].double_in_place();
-        state.iter_mut().for_each(|s| *s += sum);
+impl HashDomain {
+    /// Capacity element encoding the domain and an input length.
+    pub fn tag(self, length: usize) -> Fr {
+        assert!(length < 1 << 32, "Input too long for domain tag.");
+        Fr::from(((self as u64) << 32) + length as u64)
     }
-    for rc in RC3.2 {
-        state.iter_mut().zip(rc).for_each(|(x, rc)| *x += rc);
-        state
-            .iter_mut()
-            .for_each(|x| *x *= x.square().square_in_place());
-        mat_full_3(state);
+}
+
+/// Compress arbitrary length inputs with domain separation.
+///
+/// Same 16-ary tree as [`compress`], but each permutation absorbs at most 15
+/// elements and the last state element is the [`HashDomain::tag`] of the length of
+/// the input it covers. So inputs differing in length or domain never share a
+/// permutation input, and zero padding is unambiguous.
+pub fn compress_with_domain(domain: HashDomain, input: &[Fr]) -> Fr {
+    let mut state = [Fr::ZERO; 16];
+    if input.len() <= 15 {
+        state[..input.len()].copy_from_slice(input);
+    } else {
+        // Compute the largest power of 15 < input.len();
+        let mut chunk = 15;
+        while chunk * 15 < input.len() {
+            chunk *= 15;
+        }
+        if chunk == 15 {
+            // Children are single permutations, compute them as a batch.
+            let mut leaves = [[Fr::ZERO; 16]; 15];
+            let count = input.len().div_ceil(15);
+            for (leaf, chunk) in leaves.iter_mut().zip(input.chunks(15)) {
+                leaf[..chunk.len()].copy_from_slice(chunk);
+                leaf[15] = domain.tag(chunk.len());
+            }
+            permute_16_many(&mut leaves[..count]);
+            for (s, leaf) in state.iter_mut().zip(&leaves[..count]) {
+                *s = leaf[0];
+            }
+        } else {
+            for (s, chunk) in state.iter_mut().zip(input.chunks(chunk)) {
+                *s = compress_with_domain(domain, chunk);
+            }
+        }
     }
+    state[15] = domain.tag(input.len());
+    permute_16(&mut state);
+    state[0]
+}
+
+pub fn permute_3(state: &mut [Fr; 3]) {
+    COUNT_3.fetch_add(1, Ordering::Relaxed);
+    POSEIDON2_3.permute(state);
+}
+
+/// Permute many independent states, see [`Poseidon2::permute_many`].
+pub fn permute_3_many(states: &mut [[Fr; 3]]) {
+    COUNT_3.fetch_add(states.len() as u32, Ordering::Relaxed);
+    POSEIDON2_3.permute_many(states);
 }
 
-// OPT: Time spend: 53% in `mat_partial_16`, 31% in x^5, 11% in `mat_full_16`.
 pub fn permute_16(state: &mut [Fr; 16]) {
     COUNT_16.fetch_add(1, Ordering::Relaxed);
-    mat_full_16(state);
-    for rc in RC16.0 {
-        // TODO: Combine passes?
-        // Should be able to fold the linear layer into the Montgomery reduction.
-        state.iter_mut().zip(rc).for_each(|(x, rc)| *x += rc);
-        state
-            .iter_mut()
-            .for_each(|x| *x *= x.square().square_in_place());
-        mat_full_16(state);
-    }
-    for rc in RC16.1 {
-        state[0] += rc;
-        state[0] *= state[0].square().square_in_place();
-        mat_partial_16(state);
-    }
-    for rc in RC16.2 {
-        state.iter_mut().zip(rc).for_each(|(x, rc)| *x += rc);
-        state
-            .iter_mut()
-            .for_each(|x| *x *= x.square().square_in_place());
-        mat_full_16(state);
-    }
+    POSEIDON2_16.permute(state);
+}
+
+/// Permute many independent states, see [`Poseidon2::permute_many`].
+pub fn permute_16_many(states: &mut [[Fr; 16]]) {
+    COUNT_16.fetch_add(states.len() as u32, Ordering::Relaxed);
+    POSEIDON2_16.permute_many(states);
+}
+
+pub fn permute_24(state: &mut [Fr; 24]) {
+    COUNT_24.fetch_add(1, Ordering::Relaxed);
+    POSEIDON2_24.permute(state);
+}
+
+/// Permute many independent states, see [`Poseidon2::permute_many`].
+pub fn permute_24_many(states: &mut [[Fr; 24]]) {
+    COUNT_24.fetch_add(states.len() as u32, Ordering::Relaxed);
+    POSEIDON2_24.permute_many(states);
 }
 
 pub fn mat_full_3(state: &mut [Fr; 3]) {
-    // Matrix circ(2, 1, 1)
-    let sum: Fr = state.iter().sum();
-    state.iter_mut().for_each(|s| *s += sum);
+    Poseidon2::mat_external(state);
 }
 
 pub fn mat_full_4(state: &mut [Fr; 4]) {
@@ -133,99 +230,26 @@ pub fn mat_full_4(state: &mut [Fr; 4]) {
 }
 
 pub fn mat_full_16(state: &mut [Fr; 16]) {
-    // TODO: Use array_chunks_mut when it is stable
-    let mut sum = [Fr::ZERO; 4];
-    state.chunks_exact_mut(4).for_each(|s| {
-        let s: &mut [Fr; 4] = s.try_into().unwrap();
-        mat_full_4(s);
-        sum.iter_mut().zip(s.iter()).for_each(|(sum, s)| *sum += s);
-    });
-    state.chunks_exact_mut(4).for_each(|s| {
-        let s: &mut [Fr; 4] = s.try_into().unwrap();
-        s.iter_mut().zip(sum.iter()).for_each(|(s, sum)| *s += sum);
-    });
+    Poseidon2::mat_external(state);
+}
+
+pub fn mat_full_24(state: &mut [Fr; 24]) {
+    Poseidon2::mat_external(state);
 }
 
 /// Computes a 16x16 partial matrix.
 /// These meet requirements set out in Poseidon2 paper.
 /// Ones + Diag(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 14, 16, 17)
-// OPT: This would benefit from delayed reduction. If we do this using
-// additional bits in each limb, say base 2^43 over [u64; 6], we can do
-// ~six rounds before needing to propagate carries and reductions. These
-// can then be done in SIMD.
 pub fn mat_partial_16(state: &mut [Fr; 16]) {
-    let sum: Fr = state.iter().sum();
-
-    // 0
-    state[0] = Fr::ZERO;
-    // 1
-    // 2
-    state[2].double_in_place();
-    // 3
-    state[3] += state[3].double();
-    // 4
-    state[4].double_in_place().double_in_place();
-    // 5
-    state[5] += state[5].double().double_in_place();
-    // 6
-    state[6].double_in_place();
-    state[6] += state[6].double();
-    // 7
-    let t = state[7];
-    state[7]
-        .double_in_place()
-        .double_in_place()
-        .double_in_place();
-    state[7] -= t;
-    // 8
-    state[8]
-        .double_in_place()
-        .double_in_place()
-        .double_in_place();
-    // 9
-    state[9] += state[9].double().double_in_place().double_in_place();
-    // 10
-    state[10].double_in_place();
-    state[10] += state[10].double().double_in_place();
-    // 11
-    let t = state[11];
-    state[11].double_in_place();
-    state[11] += state[11].double().double_in_place();
-    state[11] += t;
-    // 13
-    let t1 = state[12];
-    state[12].double_in_place();
-    let t2 = state[12];
-    state[12]
-        .double_in_place()
-        .double_in_place()
-        .double_in_place();
-    state[12] -= t1;
-    state[12] -= t2;
-    // 14
-    state[13].double_in_place();
-    let t2 = state[13];
-    state[13]
-        .double_in_place()
-        .double_in_place()
-        .double_in_place();
-    state[13] -= t2;
-    // 16
-    state[14]
-        .double_in_place()
-        .double_in_place()
-        .double_in_place()
-        .double_in_place();
-    // 17
-    let t = state[15];
-    state[15]
-        .double_in_place()
-        .double_in_place()
-        .double_in_place()
-        .double_in_place();
-    state[15] += t;
-
-    state.iter_mut().for_each(|s| *s += sum);
+    POSEIDON2_16.mat_internal(state);
+}
+
+/// Computes a 24x24 partial matrix.
+/// Ones + Diag(0, 1, 2, ..., 23)
+/// The minimal polynomials of its first 48 powers are irreducible of degree 24,
+/// as required by the Poseidon2 paper.
+pub fn mat_partial_24(state: &mut [Fr; 24]) {
+    POSEIDON2_24.mat_internal(state);
 }
 
 #[cfg(test)]
@@ -234,9 +258,127 @@ mod test {
         super::*,
         ark_ff::{BigInteger, MontFp, PrimeField},
         hex,
-        std::array,
+        rand::{Rng, SeedableRng},
+        rand_chacha::ChaCha20Rng,
+        std::{array, sync::Arc},
+        zkhash::{
+            fields::bn256::FpBN256,
+            poseidon2::{poseidon2::Poseidon2 as Reference, poseidon2_params::Poseidon2Params},
+        },
     };
 
+    // Hand written linear layers with full reductions.
+    fn mat_full_16_reference(state: &mut [Fr; 16]) {
+        // TODO: Use array_chunks_mut when it is stable
+        let mut sum = [Fr::ZERO; 4];
+        state.chunks_exact_mut(4).for_each(|s| {
+            let s: &mut [Fr; 4] = s.try_into().unwrap();
+            mat_full_4(s);
+            sum.iter_mut().zip(s.iter()).for_each(|(sum, s)| *sum += s);
+        });
+        state.chunks_exact_mut(4).for_each(|s| {
+            let s: &mut [Fr; 4] = s.try_into().unwrap();
+            s.iter_mut().zip(sum.iter()).for_each(|(s, sum)| *s += sum);
+        });
+    }
+
+    fn mat_partial_16_reference(state: &mut [Fr; 16]) {
+        let sum: Fr = state.iter().sum();
+
+        // 0
+        state[0] = Fr::ZERO;
+        // 1
+        // 2
+        state[2].double_in_place();
+        // 3
+        state[3] += state[3].double();
+        // 4
+        state[4].double_in_place().double_in_place();
+        // 5
+        state[5] += state[5].double().double_in_place();
+        // 6
+        state[6].double_in_place();
+        state[6] += state[6].double();
+        // 7
+        let t = state[7];
+        state[7]
+            .double_in_place()
+            .double_in_place()
+            .double_in_place();
+        state[7] -= t;
+        // 8
+        state[8]
+            .double_in_place()
+            .double_in_place()
+            .double_in_place();
+        // 9
+        state[9] += state[9].double().double_in_place().double_in_place();
+        // 10
+        state[10].double_in_place();
+        state[10] += state[10].double().double_in_place();
+        // 11
+        let t = state[11];
+        state[11].double_in_place();
+        state[11] += state[11].double().double_in_place();
+        state[11] += t;
+        // 13
+        let t1 = state[12];
+        state[12].double_in_place();
+        let t2 = state[12];
+        state[12]
+            .double_in_place()
+            .double_in_place()
+            .double_in_place();
+        state[12] -= t1;
+        state[12] -= t2;
+        // 14
+        state[13].double_in_place();
+        let t2 = state[13];
+        state[13]
+            .double_in_place()
+            .double_in_place()
+            .double_in_place();
+        state[13] -= t2;
+        // 16
+        state[14]
+            .double_in_place()
+            .double_in_place()
+            .double_in_place()
+            .double_in_place();
+        // 17
+        let t = state[15];
+        state[15]
+            .double_in_place()
+            .double_in_place()
+            .double_in_place()
+            .double_in_place();
+        state[15] += t;
+
+        state.iter_mut().for_each(|s| *s += sum);
+    }
+
+    #[test]
+    fn test_linear_layers_16() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for _ in 0..100 {
+            let state: [Fr; 16] = array::from_fn(|_| rng.gen());
+            let (mut a, mut b) = (state, state);
+            mat_full_16(&mut a);
+            mat_full_16_reference(&mut b);
+            assert_eq!(a, b);
+            let (mut a, mut b) = (state, state);
+            mat_partial_16(&mut a);
+            mat_partial_16_reference(&mut b);
+            assert_eq!(a, b);
+        }
+        // Extremal values
+        let mut a = [-Fr::ONE; 16];
+        let mut b = a;
+        mat_partial_16(&mut a);
+        mat_partial_16_reference(&mut b);
+        assert_eq!(a, b);
+    }
+
     #[test]
     fn assert_hardcoded_field() {
         assert_eq!(
@@ -325,6 +467,521 @@ mod test {
         );
     }
 
+    /// The HorizenLabs reference implementation (crate `zkhash` 0.2.0) with the
+    /// round constants and diagonal of `instance`.
+    fn reference<const WIDTH: usize>(instance: &Poseidon2<WIDTH>) -> Reference<FpBN256> {
+        let convert = |x: &Fr| FpBN256::from_be_bytes_mod_order(&x.into_bigint().to_bytes_be());
+        let constants = &instance.constants;
+        let full = |rounds: &[[Fr; WIDTH]]| -> Vec<Vec<FpBN256>> {
+            rounds
+                .iter()
+                .map(|r| r.iter().map(convert).collect())
+                .collect()
+        };
+        let partial = constants.partial.iter().map(|c| {
+            let mut round = vec![FpBN256::from(0_u64); WIDTH];
+            round[0] = convert(c);
+            round
+        });
+        let round_constants: Vec<_> = full(&constants.initial)
+            .into_iter()
+            .chain(partial)
+            .chain(full(&constants.terminal))
+            .collect();
+        let diagonal: Vec<_> = instance.diagonal.map(FpBN256::from).to_vec();
+        Reference::new(&Arc::new(Poseidon2Params::new(
+            WIDTH,
+            5,
+            2 * constants.initial.len(),
+            constants.partial.len(),
+            &diagonal,
+            &[],
+            &round_constants,
+        )))
+    }
+
+    /// Compare with [`reference`] on the test vector input and a random input.
+    fn check_reference<const WIDTH: usize>(instance: &Poseidon2<WIDTH>) {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let to_reference = |x: Fr| FpBN256::from_be_bytes_mod_order(&x.into_bigint().to_bytes_be());
+        let from_reference =
+            |x: &FpBN256| Fr::from_be_bytes_mod_order(&x.into_bigint().to_bytes_be());
+        let reference = reference(instance);
+        for input in [
+            array::from_fn(|i| Fr::from(i as u64)),
+            array::from_fn(|_| rng.gen()),
+        ] {
+            let mut state: [Fr; WIDTH] = input;
+            instance.permute(&mut state);
+            let expected = reference.permutation(&input.map(to_reference));
+            assert_eq!(
+                state.to_vec(),
+                expected.iter().map(from_reference).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_reference() {
+        check_reference(&POSEIDON2_2);
+        check_reference(&POSEIDON2_3);
+        check_reference(&POSEIDON2_4);
+        check_reference(&POSEIDON2_8);
+        check_reference(&POSEIDON2_12);
+        check_reference(&POSEIDON2_16);
+        check_reference(&POSEIDON2_24);
+        // The reference implementation stops at width 24, width 28 uses the same
+        // external matrix construction.
+    }
+
+    #[test]
+    fn test_vector_24() {
+        // HorizenLabs publishes no BN254 instance for t = 24, so there is no
+        // reference vector. Constants are from the Grain LFSR of the HorizenLabs
+        // generator, which reproduces RC3 and RC16, rounds and diagonal are checked
+        // below. This vector is reproduced by the reference implementation with the
+        // same parameters in `test_reference`.
+        let mut state = array::from_fn(|i| Fr::from(i as u64));
+        permute_24(&mut state);
+        assert_eq!(
+            state,
+            [
+                MontFp!(
+                    "15415073202406910938455666775556707497085635029937138762626929821618522437469"
+                ),
+                MontFp!(
+                    "7107481674076835096477939564509701116136849055781555180461459612385229869189"
+                ),
+                MontFp!(
+                    "19980111240202108495854743605571210460272598451410034034144658959199453295337"
+                ),
+                MontFp!(
+                    "21457916594031911203287052182741132398011674318012557126080395642136061657786"
+                ),
+                MontFp!(
+                    "12477432513080485390107686035539833211161166285527223844556106631434436054395"
+                ),
+                MontFp!(
+                    "17897844031756952453739072665907199581615797185215454435814577505966337712389"
+                ),
+                MontFp!(
+                    "18350618225726775792708697312155845173030399955434869492812053158146611006435"
+                ),
+                MontFp!(
+                    "19918091691018030979319577231020538456931920339042231223014776999127674770286"
+                ),
+                MontFp!(
+                    "9568375645065221480285823041968218018300279677800648693131761323837030665824"
+                ),
+                MontFp!(
+                    "4810888054279892652780367345760973487807998880366168914804224286424575399426"
+                ),
+                MontFp!(
+                    "21826042401378316224325343801871692445027544280272913617356590063766684969240"
+                ),
+                MontFp!(
+                    "9499883699940292631154350948928743259378614911077255046988379053337421046857"
+                ),
+                MontFp!(
+                    "12713640092910013696864905349044655610978447414514535420133990619481163565066"
+                ),
+                MontFp!(
+                    "16764626074837744611724047342342127504240877008956742177492887171608261122172"
+                ),
+                MontFp!(
+                    "9168121763904670988511109239180141405009778539611057649048819370634388051028"
+                ),
+                MontFp!(
+                    "12780149336301041794757459286816237117299277855704784217101664717338837091942"
+                ),
+                MontFp!(
+                    "11171266782947651021721797672083411282078490057119218914145140084703540559807"
+                ),
+                MontFp!(
+                    "5001840850150585897272048454612814465498892517540343648944458477227531252674"
+                ),
+                MontFp!(
+                    "19173253662678170608659108475621177655949402207916035497866988732767673778102"
+                ),
+                MontFp!(
+                    "9349772885567184080604231776223086551902426637836221664635346277841101302480"
+                ),
+                MontFp!(
+                    "21344237959431859339933569462306347791996096202287558655319062688499058284615"
+                ),
+                MontFp!(
+                    "12142637433060300051478418847135289336194057983006050793790697021656023827619"
+                ),
+                MontFp!(
+                    "15643759190449038268611480066203817221360372678741051501145176428969245193324"
+                ),
+                MontFp!(
+                    "2382206343537144097275794330596675953558944745562819854090530043189559916065"
+                ),
+            ]
+        );
+    }
+
+    /// Round numbers $(R_F, R_P)$ for 128 bits of security with S-box $x^5$,
+    /// including the security margin, as in `poseidon2_rust_params.sage`.
+    fn round_numbers(t: usize) -> (usize, usize) {
+        let (alpha, security) = (5.0_f64, 128.0_f64);
+        let log2_p = Fr::MODULUS
+            .0
+            .iter()
+            .rev()
+            .fold(0.0, |acc, limb| acc * 2.0_f64.powi(64) + *limb as f64)
+            .log2();
+        let log_alpha_2 = 1.0 / alpha.log2();
+        let tf = t as f64;
+        let secure = |rf: usize, rp: usize| {
+            let (rf_f, rp_f) = (rf as f64, rp as f64);
+            let statistical = if security <= (log2_p - (alpha - 1.0) / 2.0).floor() * (tf + 1.0) {
+                6.0
+            } else {
+                10.0
+            };
+            let interpolation = 1.0
+                + (log_alpha_2 * security.min(log2_p.floor())).ceil()
+                + (tf.log2() / alpha.log2()).ceil()
+                - rp_f;
+            let groebner_1 = log_alpha_2 * security.min(log2_p) - rp_f;
+            let groebner_2 =
+                tf - 1.0 + log_alpha_2 * (security / (tf + 1.0)).min(log2_p / 2.0) - rp_f;
+            let groebner_3 = (tf - 2.0 + security / (2.0 * alpha.log2()) - rp_f) / (tf - 1.0);
+            let bound = [
+                statistical,
+                interpolation,
+                groebner_1,
+                groebner_2,
+                groebner_3,
+            ]
+            .into_iter()
+            .map(f64::ceil)
+            .fold(f64::MIN, f64::max);
+            if rf_f < bound {
+                return false;
+            }
+            // Binomial Gröbner basis attack.
+            let r = t / 3;
+            let over = (rf - 1) * t + rp + r + r * (rf / 2) + rp + 5;
+            let under = r * (rf / 2) + rp + 5;
+            let log2_binomial: f64 = (1..=under)
+                .map(|i| ((over - under + i) as f64 / i as f64).log2())
+                .sum();
+            (2.0 * log2_binomial).ceil() >= security
+        };
+        let (mut best, mut min_cost) = ((0, 0), usize::MAX);
+        for rp in 1..500 {
+            for rf in (4..100).step_by(2) {
+                if secure(rf, rp) {
+                    let (rf, rp) = (rf + 2, (rp as f64 * 1.075).ceil() as usize);
+                    let cost = t * rf + rp;
+                    if cost < min_cost || (cost == min_cost && rf < best.0) {
+                        (best, min_cost) = ((rf, rp), cost);
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn test_round_numbers() {
+        fn check<const WIDTH: usize>(instance: &Poseidon2<WIDTH>) {
+            let constants = &instance.constants;
+            let rounds = (2 * constants.initial.len(), constants.partial.len());
+            assert_eq!(rounds, round_numbers(WIDTH), "Width {WIDTH}");
+        }
+        check(&POSEIDON2_2);
+        check(&POSEIDON2_3);
+        check(&POSEIDON2_4);
+        check(&POSEIDON2_8);
+        check(&POSEIDON2_12);
+        check(&POSEIDON2_16);
+        check(&POSEIDON2_24);
+        check(&POSEIDON2_28);
+    }
+
+    /// Polynomials with coefficients from low to high degree.
+    fn mul_mod(a: &[Fr], b: &[Fr], modulus: &[Fr]) -> Vec<Fr> {
+        let mut product = vec![Fr::ZERO; a.len() + b.len() - 1];
+        for (i, a) in a.iter().enumerate() {
+            for (j, b) in b.iter().enumerate() {
+                product[i + j] += *a * b;
+            }
+        }
+        rem(product, modulus)
+    }
+
+    fn rem(mut a: Vec<Fr>, modulus: &[Fr]) -> Vec<Fr> {
+        let degree = modulus.len() - 1;
+        let lead = modulus[degree].inverse().unwrap();
+        while a.len() > degree {
+            let factor = a.pop().unwrap() * lead;
+            let offset = a.len() - degree;
+            for (a, m) in a[offset..].iter_mut().zip(modulus) {
+                *a -= factor * m;
+            }
+        }
+        a
+    }
+
+    fn degree_of_gcd(mut a: Vec<Fr>, mut b: Vec<Fr>) -> usize {
+        let trim = |p: &mut Vec<Fr>| {
+            while p.last() == Some(&Fr::ZERO) {
+                p.pop();
+            }
+        };
+        trim(&mut a);
+        trim(&mut b);
+        while !b.is_empty() {
+            let mut r = rem(a, &b);
+            trim(&mut r);
+            (a, b) = (b, r);
+        }
+        a.len() - 1
+    }
+
+    /// Checks that $M_I = 1 + \operatorname{diag}(d)$ satisfies the condition of
+    /// `poseidon2_rust_params.sage`: the minimal polynomials of $M_I^i$ for
+    /// $i ≤ 2t$ are irreducible of degree $t$. This rules out invariant subspaces
+    /// and implies $M_I$ is invertible.
+    fn check_internal_matrix(diagonal: &[u64]) {
+        let t = diagonal.len();
+        // Characteristic polynomial ∏(x - d_i) - ∑_j ∏_{i ≠ j}(x - d_i).
+        let linear = |d: u64| vec![-Fr::from(d), Fr::ONE];
+        let product = |skip: Option<usize>| {
+            diagonal
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| Some(*i) != skip)
+                .fold(vec![Fr::ONE], |p, (_, d)| {
+                    let mut result = vec![Fr::ZERO; p.len() + 1];
+                    for (i, c) in p.iter().enumerate() {
+                        result[i] += *c * linear(*d)[0];
+                        result[i + 1] += c;
+                    }
+                    result
+                })
+        };
+        let mut f = product(None);
+        for j in 0..t {
+            f.iter_mut()
+                .zip(product(Some(j)))
+                .for_each(|(f, p)| *f -= p);
+        }
+        assert_ne!(f[0], Fr::ZERO, "Singular internal matrix.");
+
+        // Frobenius y ↦ y^p on F_p[x] / f as images of the powers of x.
+        let x = vec![Fr::ZERO, Fr::ONE];
+        let mut x_p = vec![Fr::ONE];
+        for bit in Fr::MODULUS.to_bits_be() {
+            x_p = mul_mod(&x_p, &x_p, &f);
+            if bit {
+                x_p = mul_mod(&x_p, &x, &f);
+            }
+        }
+        let mut powers = vec![vec![Fr::ONE]];
+        for _ in 1..t {
+            powers.push(mul_mod(powers.last().unwrap(), &x_p, &f));
+        }
+        let frobenius = |y: &[Fr], k: usize| {
+            let mut y = y.to_vec();
+            for _ in 0..k {
+                let mut image = vec![Fr::ZERO; t];
+                for (c, power) in y.iter().zip(&powers) {
+                    image.iter_mut().zip(power).for_each(|(i, p)| *i += *c * p);
+                }
+                y = image;
+            }
+            y
+        };
+        let pad = |mut y: Vec<Fr>| {
+            y.resize(t, Fr::ZERO);
+            y
+        };
+        let primes: Vec<usize> = (2..=t)
+            .filter(|q| t.is_multiple_of(*q) && (2..*q).all(|d| !q.is_multiple_of(d)))
+            .collect();
+
+        // Rabin's irreducibility test for f.
+        assert_eq!(frobenius(&pad(x.clone()), t), pad(x.clone()));
+        for q in &primes {
+            let mut h = frobenius(&pad(x.clone()), t / q);
+            h[1] -= Fr::ONE;
+            assert_eq!(degree_of_gcd(f.clone(), h), 0, "Reducible internal matrix.");
+        }
+
+        // With f irreducible, M_I^i has an irreducible minimal polynomial of degree t
+        // iff x^i is in no proper subfield of F_p[x] / f.
+        let mut y = pad(vec![Fr::ONE]);
+        for _ in 1..=2 * t {
+            y = pad(mul_mod(&y, &x, &f));
+            for q in &primes {
+                assert_ne!(frobenius(&y, t / q), y, "Invariant subspace.");
+            }
+        }
+    }
+
+    #[test]
+    fn test_internal_matrices() {
+        // Widths 2 and 3 use the fixed matrices of the paper instead.
+        check_internal_matrix(&POSEIDON2_4.diagonal);
+        check_internal_matrix(&POSEIDON2_8.diagonal);
+        check_internal_matrix(&POSEIDON2_12.diagonal);
+        check_internal_matrix(&POSEIDON2_16.diagonal);
+        check_internal_matrix(&POSEIDON2_24.diagonal);
+        check_internal_matrix(&POSEIDON2_28.diagonal);
+        // A repeated entry d makes x - d a factor.
+        let mut repeated = POSEIDON2_16.diagonal;
+        repeated[12] = 11;
+        assert!(std::panic::catch_unwind(|| check_internal_matrix(&repeated)).is_err());
+    }
+
+    #[test]
+    fn test_permute_16_many() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let mut states: Vec<[Fr; 16]> = (0..2 * LANES + 3)
+            .map(|_| array::from_fn(|_| rng.gen()))
+            .collect();
+        let mut expected = states.clone();
+        expected.iter_mut().for_each(permute_16);
+        permute_16_many(&mut states);
+        assert_eq!(states, expected);
+    }
+
+    #[test]
+    fn test_vectors_generic() {
+        // Computed with an independent Python implementation, and reproduced by the
+        // reference implementation in `test_reference`.
+        let mut state = array::from_fn(|i| Fr::from(i as u64));
+        POSEIDON2_2.permute(&mut state);
+        assert_eq!(
+            state,
+            [
+                MontFp!(
+                    "13120422956170837922441672802975889424559262309139960702680326932494325745547"
+                ),
+                MontFp!(
+                    "5923567162677888564808904842769941181302763723060647224839027357562627386465"
+                ),
+            ]
+        );
+        let mut state = array::from_fn(|i| Fr::from(i as u64));
+        POSEIDON2_4.permute(&mut state);
+        assert_eq!(
+            state,
+            [
+                MontFp!(
+                    "21883283714973905634175662134659920239002980935097368306708963037554168228382"
+                ),
+                MontFp!(
+                    "5986812122758428471295072468510859576202138360920969142817610313042796871157"
+                ),
+                MontFp!(
+                    "680724228000305016085323927980411645783327225423629262555518697763313852575"
+                ),
+                MontFp!(
+                    "14566299446719216306332043800138631171982960432934595401791782362528435718118"
+                ),
+            ]
+        );
+        let mut state = array::from_fn(|i| Fr::from(i as u64));
+        POSEIDON2_8.permute(&mut state);
+        assert_eq!(
+            state,
+            [
+                MontFp!(
+                    "17876695023579535904800583225590181610315856612294640781264951187613302524826"
+                ),
+                MontFp!(
+                    "4531260668050299301708524077128742069809509761990285465519401041813199554418"
+                ),
+                MontFp!(
+                    "8325822005642683509873694509490951985989464263303136629843098212312077049841"
+                ),
+                MontFp!(
+                    "2369335258427017988077725500317735517516332571815719857075896172980045819117"
+                ),
+                MontFp!(
+                    "17860139957924388438359862713474992735593373511275255305349793553495100481296"
+                ),
+                MontFp!(
+                    "2748347673426420268694219752706020958791158456505073991284811589236714066138"
+                ),
+                MontFp!(
+                    "21772446362592104051681577834887422109824741650807603202847923574828288738703"
+                ),
+                MontFp!(
+                    "7208511285102821084561299277211046212077349387904143625985209974076465534404"
+                ),
+            ]
+        );
+        let mut state = array::from_fn(|i| Fr::from(i as u64));
+        POSEIDON2_12.permute(&mut state);
+        assert_eq!(
+            state,
+            [
+                MontFp!(
+                    "8661893231573884831110882409993711167195268869685726052515350835023839797507"
+                ),
+                MontFp!(
+                    "16577689745162661849520429071620989874576157424164583169083634453431050751754"
+                ),
+                MontFp!(
+                    "20470494037931192963085090242333385046572000199153953508146806406070878061289"
+                ),
+                MontFp!(
+                    "11732733891186865813866380881316045496651343855576416733445205538314298602708"
+                ),
+                MontFp!(
+                    "9494931658772784239671902960487778473860978807472570024234001679873232867037"
+                ),
+                MontFp!(
+                    "17922242369849771467155515504697622118818743955346230690580189611463104786645"
+                ),
+                MontFp!(
+                    "3462664495139595870080227136414917578365731284787181492854078330302726489369"
+                ),
+                MontFp!(
+                    "879013225702296735815144604509557752169990299720193715964044683178485659297"
+                ),
+                MontFp!(
+                    "17866035892374000817465034021419183626811924571103945311724376503677513477858"
+                ),
+                MontFp!(
+                    "11856546579550140662363976400709900069243468525297531099224225956101934447024"
+                ),
+                MontFp!(
+                    "2479306143165471669497631905122190651081585064987626322808298420396421650442"
+                ),
+                MontFp!(
+                    "2986764635603873019711765964426066454497706531146191249847693680609990403241"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_permute_many() {
+        fn check<const WIDTH: usize>(instance: &Poseidon2<WIDTH>) {
+            let mut rng = ChaCha20Rng::from_entropy();
+            let mut states: Vec<[Fr; WIDTH]> = (0..LANES + 1)
+                .map(|_| array::from_fn(|_| rng.gen()))
+                .collect();
+            let mut expected = states.clone();
+            expected.iter_mut().for_each(|s| instance.permute(s));
+            instance.permute_many(&mut states);
+            assert_eq!(states, expected);
+        }
+        check(&POSEIDON2_2);
+        check(&POSEIDON2_3);
+        check(&POSEIDON2_8);
+        check(&POSEIDON2_24);
+        check(&POSEIDON2_28);
+    }
+
     #[test]
     fn test_vector_compress_100() {
         let input: [Fr; 100] = array::from_fn(|i| Fr::from(i as u64));
@@ -337,6 +994,46 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_compress_with_domain() {
+        let x = Fr::from(7);
+        let hashes = [
+            compress_with_domain(HashDomain::Leaf, &[x]),
+            compress_with_domain(HashDomain::Leaf, &[x, Fr::ZERO]),
+            compress_with_domain(HashDomain::Leaf, &[x; 15]),
+            compress_with_domain(HashDomain::Leaf, &[x; 16]),
+            compress_with_domain(HashDomain::Node, &[x]),
+            compress_with_domain(HashDomain::Column, &[x]),
+            compress_with_domain(HashDomain::Salt, &[x]),
+            compress_with_domain(HashDomain::Separator, &[x]),
+            compress_with_domain(HashDomain::Label, &[x]),
+            compress(&[x]),
+        ];
+        for (i, a) in hashes.iter().enumerate() {
+            assert!(hashes[..i].iter().all(|b| a != b));
+        }
+
+        // The batched and recursive paths agree with the definition.
+        let input: Vec<Fr> = (0..300).map(Fr::from).collect();
+        let tree = |input: &[Fr], chunk| {
+            let mut state = [Fr::ZERO; 16];
+            for (s, chunk) in state.iter_mut().zip(input.chunks(chunk)) {
+                *s = compress_with_domain(HashDomain::Column, chunk);
+            }
+            state[15] = HashDomain::Column.tag(input.len());
+            permute_16(&mut state);
+            state[0]
+        };
+        assert_eq!(
+            compress_with_domain(HashDomain::Column, &input[..200]),
+            tree(&input[..200], 15)
+        );
+        assert_eq!(
+            compress_with_domain(HashDomain::Column, &input),
+            tree(&input, 225)
+        );
+    }
+
     #[test]
     fn test_vector_compress_10000() {
         let input: [Fr; 10_000] = array::from_fn(|i| Fr::from(i as u64));
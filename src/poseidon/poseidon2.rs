@@ -0,0 +1,179 @@
+//! Poseidon2 permutation over BN254, generic in the state width.
+use {
+    super::{
+        constants::RoundConstants,
+        grain,
+        redundant::{mat_full_4_redundant, Redundant},
+    },
+    ark_bn254::Fr,
+    ark_ff::Field,
+    std::{array, ops::Add},
+};
+
+/// Number of states [`Poseidon2::permute_many`] interleaves.
+pub const LANES: usize = 4;
+
+/// Growth budget for [`Redundant`] limbs, which start below 2^43.
+const HEADROOM: u64 = 1 << 21;
+
+/// A Poseidon2 instance with S-box $x^5$.
+///
+/// The external matrix is circ(2, 1, …, 1) for widths 2 and 3, $M_4$ for width 4
+/// and circ(2·M_4, M_4, …, M_4) for larger multiples of four. The internal matrix
+/// is Ones + Diag(`diagonal`). The tests check the round numbers and diagonal of
+/// each instance against the conditions of the reference parameter script.
+#[derive(Clone, Debug)]
+pub struct Poseidon2<const WIDTH: usize> {
+    pub constants: RoundConstants<Fr, WIDTH>,
+    pub diagonal: [u64; WIDTH],
+    /// Partial rounds between reductions of the redundant state.
+    rounds_per_reduction: usize,
+}
+
+impl<const WIDTH: usize> Poseidon2<WIDTH> {
+    /// Instance with round constants from the Grain LFSR.
+    pub fn new(full_rounds: usize, partial_rounds: usize, diagonal: [u64; WIDTH]) -> Self {
+        Self::with_constants(
+            grain::round_constants(full_rounds, partial_rounds),
+            diagonal,
+        )
+    }
+
+    pub fn with_constants(constants: RoundConstants<Fr, WIDTH>, diagonal: [u64; WIDTH]) -> Self {
+        assert!(
+            matches!(WIDTH, 2 | 3) || (WIDTH >= 4 && WIDTH.is_multiple_of(4)),
+            "Unsupported Poseidon2 width."
+        );
+        // Limbs grow by at most this factor per partial round.
+        let growth = diagonal.iter().max().unwrap() + WIDTH as u64;
+        let mut rounds_per_reduction = 0;
+        let mut bound = growth;
+        while bound < HEADROOM {
+            bound *= growth;
+            rounds_per_reduction += 1;
+        }
+        assert!(rounds_per_reduction > 0, "Internal diagonal too large.");
+        Self {
+            constants,
+            diagonal,
+            rounds_per_reduction,
+        }
+    }
+
+    pub fn permute(&self, state: &mut [Fr; WIDTH]) {
+        self.permute_lanes(array::from_mut(state));
+    }
+
+    /// Permute many independent states. These are processed in groups of [`LANES`]
+    /// with interleaved rounds, so the dependency chains overlap.
+    pub fn permute_many(&self, states: &mut [[Fr; WIDTH]]) {
+        let mut chunks = states.chunks_exact_mut(LANES);
+        for chunk in &mut chunks {
+            self.permute_lanes::<LANES>(chunk.try_into().unwrap());
+        }
+        for state in chunks.into_remainder() {
+            self.permute(state);
+        }
+    }
+
+    /// Multiply by the external matrix.
+    pub fn mat_external(state: &mut [Fr; WIDTH]) {
+        if WIDTH < 4 {
+            let sum: Fr = state.iter().sum();
+            state.iter_mut().for_each(|s| *s += sum);
+            return;
+        }
+        let mut x = state.map(Redundant::from_fr);
+        let mut sum = [Redundant::default(); 4];
+        x.chunks_exact_mut(4).for_each(|x| {
+            let x: &mut [Redundant; 4] = x.try_into().unwrap();
+            mat_full_4_redundant(x);
+            sum.iter_mut()
+                .zip(x.iter())
+                .for_each(|(sum, x)| *sum = *sum + *x);
+        });
+        if WIDTH == 4 {
+            *state = x.map(Redundant::to_fr);
+            return;
+        }
+        state.iter_mut().zip(x).enumerate().for_each(|(i, (s, x))| {
+            *s = (x + sum[i % 4]).to_fr();
+        });
+    }
+
+    /// Multiply by the internal matrix.
+    pub fn mat_internal(&self, state: &mut [Fr; WIDTH]) {
+        let x = state.map(Redundant::from_fr);
+        let sum = x.into_iter().fold(Redundant::default(), Add::add);
+        state
+            .iter_mut()
+            .zip(x)
+            .zip(self.diagonal)
+            .for_each(|((s, x), d)| {
+                *s = (x.mul_small(d) + sum).to_fr();
+            });
+    }
+
+    // OPT: Time spend at width 16 before delayed reduction: 53% in the internal
+    // matrix, 31% in x^5, 11% in the external matrix.
+    fn permute_lanes<const L: usize>(&self, states: &mut [[Fr; WIDTH]; L]) {
+        states.iter_mut().for_each(Self::mat_external);
+        for rc in &self.constants.initial {
+            Self::full_round(states, rc);
+        }
+        self.partial_rounds(states);
+        for rc in &self.constants.terminal {
+            Self::full_round(states, rc);
+        }
+    }
+
+    fn full_round<const L: usize>(states: &mut [[Fr; WIDTH]; L], rc: &[Fr; WIDTH]) {
+        // TODO: Combine passes?
+        // Should be able to fold the linear layer into the Montgomery reduction.
+        for state in states.iter_mut() {
+            state.iter_mut().zip(rc).for_each(|(x, rc)| *x += rc);
+        }
+        states
+            .iter_mut()
+            .flatten()
+            .for_each(|x| *x *= x.square().square_in_place());
+        states.iter_mut().for_each(Self::mat_external);
+    }
+
+    /// Only the first element passes the S-box in partial rounds, so the others
+    /// stay in redundant form and are only reduced every few rounds.
+    /// The states are transposed to structure-of-arrays so each step runs across lanes.
+    fn partial_rounds<const L: usize>(&self, states: &mut [[Fr; WIDTH]; L]) {
+        let mut first: [Fr; L] = array::from_fn(|l| states[l][0]);
+        // Row zero is unused, the first element is kept in `first`.
+        let mut rows: [[Redundant; L]; WIDTH] =
+            array::from_fn(|i| array::from_fn(|l| Redundant::from_fr(states[l][i])));
+        for (round, &rc) in self.constants.partial.iter().enumerate() {
+            first.iter_mut().for_each(|x| {
+                *x += rc;
+                *x *= x.square().square_in_place();
+            });
+            let x = first.map(Redundant::from_fr);
+            let mut sum = x;
+            for row in &rows[1..] {
+                sum.iter_mut().zip(row).for_each(|(s, x)| *s = *s + *x);
+            }
+            first = array::from_fn(|l| (x[l].mul_small(self.diagonal[0]) + sum[l]).to_fr());
+            for (row, d) in rows[1..].iter_mut().zip(&self.diagonal[1..]) {
+                row.iter_mut()
+                    .zip(&sum)
+                    .for_each(|(x, s)| *x = x.mul_small(*d) + *s);
+            }
+            if (round + 1).is_multiple_of(self.rounds_per_reduction) {
+                rows[1..].iter_mut().flatten().for_each(|x| *x = x.reduce());
+            }
+        }
+        for (l, state) in states.iter_mut().enumerate() {
+            state[0] = first[l];
+            state[1..]
+                .iter_mut()
+                .zip(&rows[1..])
+                .for_each(|(s, row)| *s = row[l].to_fr());
+        }
+    }
+}
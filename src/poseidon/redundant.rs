@@ -0,0 +1,141 @@
+//! Delayed reduction arithmetic for the Poseidon2 linear layers.
+//!
+//! Elements are kept in base $2^{43}$ over `[u64; 6]`, which leaves 21 bits of
+//! headroom per limb. Additions and multiplications by small constants are
+//! then carry free limb-wise operations that the compiler can vectorize. The
+//! linear layers commute with the Montgomery form, so it is used as is.
+use {
+    ark_bn254::Fr,
+    ark_ff::{BigInt, PrimeField},
+    std::{array, ops::Add},
+};
+
+const BITS: usize = 43;
+const MASK: u64 = (1 << BITS) - 1;
+const MODULUS: [u64; 4] = Fr::MODULUS.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Redundant(pub [u64; 6]);
+
+impl Redundant {
+    pub fn from_fr(x: Fr) -> Self {
+        let words = x.0 .0;
+        Self(array::from_fn(|k| {
+            let (word, shift) = (BITS * k / 64, BITS * k % 64);
+            let low = words[word] as u128;
+            let high = words.get(word + 1).copied().unwrap_or(0) as u128;
+            ((low | high << 64) >> shift) as u64 & MASK
+        }))
+    }
+
+    /// Carry and reduce to a canonical field element.
+    pub fn to_fr(self) -> Fr {
+        // Integer value, less than 2^279.
+        let mut sums = [0_u128; 5];
+        for (k, limb) in self.0.iter().enumerate() {
+            let (word, shift) = (BITS * k / 64, BITS * k % 64);
+            let value = (*limb as u128) << shift;
+            sums[word] += value as u64 as u128;
+            sums[word + 1] += value >> 64;
+        }
+        let mut words = [0_u64; 5];
+        let mut carry = 0;
+        for (word, sum) in words.iter_mut().zip(sums) {
+            let sum = sum + carry;
+            *word = sum as u64;
+            carry = sum >> 64;
+        }
+        debug_assert_eq!(carry, 0);
+
+        // Subtract a quotient estimate q ≤ ⌊x / p⌋ that is off by at most two.
+        let top = (words[4] as u128) << 64 | words[3] as u128;
+        let quotient = (top / (MODULUS[3] as u128 + 1)) as u64;
+        let mut carry = 0;
+        let mut borrow = false;
+        for (i, word) in words.iter_mut().enumerate() {
+            let product = quotient as u128 * MODULUS.get(i).copied().unwrap_or(0) as u128 + carry;
+            carry = product >> 64;
+            let (difference, b1) = word.overflowing_sub(product as u64);
+            let (difference, b2) = difference.overflowing_sub(borrow as u64);
+            *word = difference;
+            borrow = b1 | b2;
+        }
+        debug_assert!(!borrow && words[4] == 0);
+        let mut words: [u64; 4] = words[..4].try_into().unwrap();
+        while !less_than(&words, &MODULUS) {
+            let mut borrow = false;
+            for (word, modulus) in words.iter_mut().zip(MODULUS) {
+                let (difference, b1) = word.overflowing_sub(modulus);
+                let (difference, b2) = difference.overflowing_sub(borrow as u64);
+                *word = difference;
+                borrow = b1 | b2;
+            }
+        }
+        Fr::new_unchecked(BigInt(words))
+    }
+
+    /// Reduce to canonical limbs.
+    pub fn reduce(self) -> Self {
+        Self::from_fr(self.to_fr())
+    }
+
+    pub fn mul_small(self, factor: u64) -> Self {
+        Self(self.0.map(|limb| limb * factor))
+    }
+
+    pub fn double(self) -> Self {
+        Self(self.0.map(|limb| limb << 1))
+    }
+}
+
+impl Add for Redundant {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(array::from_fn(|i| self.0[i] + other.0[i]))
+    }
+}
+
+/// Same as `mat_full_4`, the 4x4 block of the external matrix.
+pub fn mat_full_4_redundant(state: &mut [Redundant; 4]) {
+    let t0 = state[0] + state[1];
+    let t1 = state[2] + state[3];
+    let t2 = state[1].double() + t1;
+    let t3 = state[3].double() + t0;
+    let t4 = t1.double().double() + t3;
+    let t5 = t0.double().double() + t2;
+    let t6 = t3 + t5;
+    let t7 = t2 + t4;
+    *state = [t6, t5, t7, t4];
+}
+
+fn less_than(a: &[u64; 4], b: &[u64; 4]) -> bool {
+    a.iter().rev().cmp(b.iter().rev()).is_lt()
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, ark_ff::Field, rand::Rng, rand::SeedableRng, rand_chacha::ChaCha20Rng};
+
+    #[test]
+    fn test_roundtrip() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for _ in 0..1000 {
+            let x: Fr = rng.gen();
+            let y: Fr = rng.gen();
+            assert_eq!(Redundant::from_fr(x).to_fr(), x);
+            assert_eq!(
+                (Redundant::from_fr(x) + Redundant::from_fr(y)).to_fr(),
+                x + y
+            );
+            assert_eq!(
+                Redundant::from_fr(x).mul_small(1 << 20).to_fr(),
+                x * Fr::from(1 << 20)
+            );
+        }
+        assert_eq!(
+            Redundant::from_fr(-Fr::ONE).mul_small(1 << 20).to_fr(),
+            -Fr::from(1 << 20)
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Rank-1 constraint systems $(A ⋅ z) ∘ (B ⋅ z) = C ⋅ z$ and gadgets for the
+//! crate's own hash functions, so a wrapper circuit can recompute transcripts
+//! and Merkle commitments.
+//!
+//! Constraints are synthesized together with the witness: every allocated
+//! variable takes its value from the inputs. Variable zero is the constant one.
+mod poseidon;
+mod sponge;
+
+pub use self::{
+    poseidon::{
+        compress, compress_node, compress_with_domain, permute, permute_16, permute_24, permute_3,
+    },
+    sponge::Sponge,
+};
+use {
+    crate::sparse_matrix::SparseMatrix,
+    ark_bn254::Fr,
+    ark_ff::{Field, Zero},
+    std::ops::Add,
+};
+
+/// Sparse linear combination of variables, sorted by variable index.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LinearCombination(pub Vec<(usize, Fr)>);
+
+pub struct R1cs {
+    pub a: SparseMatrix,
+    pub b: SparseMatrix,
+    pub c: SparseMatrix,
+}
+
+pub struct ConstraintSystem {
+    a: Vec<(usize, usize, Fr)>,
+    b: Vec<(usize, usize, Fr)>,
+    c: Vec<(usize, usize, Fr)>,
+    constraints: usize,
+    witness: Vec<Fr>,
+}
+
+impl LinearCombination {
+    pub fn variable(index: usize) -> Self {
+        Self(vec![(index, Fr::ONE)])
+    }
+
+    pub fn constant(value: Fr) -> Self {
+        Self::from_terms([(0, value)])
+    }
+
+    /// Collect terms, merging repeated variables and dropping zeros.
+    pub fn from_terms(terms: impl IntoIterator<Item = (usize, Fr)>) -> Self {
+        let mut terms: Vec<_> = terms.into_iter().collect();
+        terms.sort_unstable_by_key(|(index, _)| *index);
+        let mut result: Vec<(usize, Fr)> = Vec::with_capacity(terms.len());
+        for (index, coefficient) in terms {
+            match result.last_mut() {
+                Some((last, sum)) if *last == index => *sum += coefficient,
+                _ => result.push((index, coefficient)),
+            }
+        }
+        result.retain(|(_, coefficient)| !coefficient.is_zero());
+        Self(result)
+    }
+
+    /// Linear combination $∑_i c_i ⋅ x_i$.
+    pub fn sum<'a>(terms: impl IntoIterator<Item = (Fr, &'a Self)>) -> Self {
+        Self::from_terms(terms.into_iter().flat_map(|(factor, lc)| {
+            lc.0.iter()
+                .map(move |(index, coefficient)| (*index, factor * coefficient))
+        }))
+    }
+
+    pub fn add_constant(&self, value: Fr) -> Self {
+        Self::from_terms(self.0.iter().copied().chain([(0, value)]))
+    }
+}
+
+impl Add for &LinearCombination {
+    type Output = LinearCombination;
+
+    fn add(self, other: Self) -> LinearCombination {
+        LinearCombination::from_terms(self.0.iter().chain(&other.0).copied())
+    }
+}
+
+impl R1cs {
+    pub fn num_constraints(&self) -> usize {
+        self.a.rows
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.a.cols
+    }
+
+    pub fn is_satisfied(&self, z: &[Fr]) -> bool {
+        let az = self.a.mul_left(z);
+        let bz = self.b.mul_left(z);
+        let cz = self.c.mul_left(z);
+        az.iter().zip(&bz).zip(&cz).all(|((a, b), c)| *a * b == *c)
+    }
+}
+
+impl ConstraintSystem {
+    pub fn new() -> Self {
+        Self {
+            a: Vec::new(),
+            b: Vec::new(),
+            c: Vec::new(),
+            constraints: 0,
+            witness: vec![Fr::ONE],
+        }
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.witness.len()
+    }
+
+    /// Allocate a new variable with the given value.
+    pub fn alloc(&mut self, value: Fr) -> LinearCombination {
+        self.witness.push(value);
+        LinearCombination::variable(self.witness.len() - 1)
+    }
+
+    /// Evaluate a linear combination on the witness.
+    pub fn value(&self, lc: &LinearCombination) -> Fr {
+        lc.0.iter()
+            .map(|(index, coefficient)| *coefficient * self.witness[*index])
+            .sum()
+    }
+
+    /// Add the constraint $a ⋅ b = c$.
+    pub fn constrain(
+        &mut self,
+        a: &LinearCombination,
+        b: &LinearCombination,
+        c: &LinearCombination,
+    ) {
+        let row = self.constraints;
+        for (matrix, lc) in [(&mut self.a, a), (&mut self.b, b), (&mut self.c, c)] {
+            matrix.extend(lc.0.iter().map(|(index, value)| (row, *index, *value)));
+        }
+        self.constraints += 1;
+    }
+
+    /// Allocate and return the product $a ⋅ b$.
+    pub fn mul(&mut self, a: &LinearCombination, b: &LinearCombination) -> LinearCombination {
+        let product = self.alloc(self.value(a) * self.value(b));
+        self.constrain(a, b, &product);
+        product
+    }
+
+    pub fn enforce_equal(&mut self, a: &LinearCombination, b: &LinearCombination) {
+        self.constrain(a, &LinearCombination::constant(Fr::ONE), b);
+    }
+
+    /// Return the constraint matrices and the satisfying assignment $z$.
+    pub fn finish(self) -> (R1cs, Vec<Fr>) {
+        let cols = self.witness.len();
+        let matrix = |entries: Vec<(usize, usize, Fr)>| SparseMatrix {
+            rows: self.constraints,
+            cols,
+            entries: entries
+                .into_iter()
+                .map(|(row, col, value)| (row * cols + col, value))
+                .collect(),
+        };
+        let r1cs = R1cs {
+            a: matrix(self.a),
+            b: matrix(self.b),
+            c: matrix(self.c),
+        };
+        (r1cs, self.witness)
+    }
+}
+
+impl Default for ConstraintSystem {
+    fn default() -> Self {
+        ConstraintSystem::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mul() {
+        let mut cs = ConstraintSystem::new();
+        let x = cs.alloc(Fr::from(3));
+        let y = cs.alloc(Fr::from(5));
+        let sum = LinearCombination::sum([(Fr::ONE, &x), (Fr::from(2), &y)]).add_constant(Fr::ONE);
+        let product = cs.mul(&sum, &x);
+        assert_eq!(cs.value(&product), Fr::from(42));
+        cs.enforce_equal(&product, &LinearCombination::constant(Fr::from(42)));
+        let (r1cs, mut z) = cs.finish();
+        assert_eq!(r1cs.num_constraints(), 2);
+        assert!(r1cs.is_satisfied(&z));
+        z[1] += Fr::ONE;
+        assert!(!r1cs.is_satisfied(&z));
+    }
+}
@@ -0,0 +1,286 @@
+//! Poseidon2 gadgets matching [`crate::poseidon`]. Linear layers are free, each
+//! S-box takes three constraints.
+use {
+    super::{ConstraintSystem, LinearCombination},
+    crate::poseidon::{
+        HashDomain, Poseidon2, POSEIDON2_12, POSEIDON2_16, POSEIDON2_24, POSEIDON2_28, POSEIDON2_3,
+        POSEIDON2_8,
+    },
+    ark_bn254::Fr,
+    ark_ff::Field,
+    std::array,
+};
+
+pub fn permute<const WIDTH: usize>(
+    cs: &mut ConstraintSystem,
+    instance: &Poseidon2<WIDTH>,
+    state: &mut [LinearCombination; WIDTH],
+) {
+    let external = matrix(Poseidon2::<WIDTH>::mat_external);
+    let internal = matrix(|state| instance.mat_internal(state));
+    *state = apply(&external, state);
+    for rc in &instance.constants.initial {
+        full_round(cs, &external, state, rc);
+    }
+    for &rc in &instance.constants.partial {
+        state[0] = sbox(cs, &state[0].add_constant(rc));
+        *state = apply(&internal, state);
+    }
+    for rc in &instance.constants.terminal {
+        full_round(cs, &external, state, rc);
+    }
+}
+
+pub fn permute_3(cs: &mut ConstraintSystem, state: &mut [LinearCombination; 3]) {
+    permute(cs, &POSEIDON2_3, state);
+}
+
+pub fn permute_16(cs: &mut ConstraintSystem, state: &mut [LinearCombination; 16]) {
+    permute(cs, &POSEIDON2_16, state);
+}
+
+pub fn permute_24(cs: &mut ConstraintSystem, state: &mut [LinearCombination; 24]) {
+    permute(cs, &POSEIDON2_24, state);
+}
+
+/// Same as [`crate::merkle_tree::Poseidon2Hash`] node compression, `family`
+/// is zero padded to `arity`.
+pub fn compress_node(
+    cs: &mut ConstraintSystem,
+    arity: usize,
+    family: &[LinearCombination],
+) -> LinearCombination {
+    match arity {
+        2 => compress_node_width(cs, &POSEIDON2_3, arity, family),
+        4 => compress_node_width(cs, &POSEIDON2_8, arity, family),
+        8 => compress_node_width(cs, &POSEIDON2_12, arity, family),
+        16 => compress_node_width(cs, &POSEIDON2_24, arity, family),
+        24 => compress_node_width(cs, &POSEIDON2_28, arity, family),
+        _ => panic!("Unsupported Merkle arity."),
+    }
+}
+
+fn compress_node_width<const WIDTH: usize>(
+    cs: &mut ConstraintSystem,
+    instance: &Poseidon2<WIDTH>,
+    arity: usize,
+    family: &[LinearCombination],
+) -> LinearCombination {
+    assert!(family.len() <= arity);
+    let mut state: [LinearCombination; WIDTH] = array::from_fn(|_| Default::default());
+    state[..family.len()].clone_from_slice(family);
+    state[WIDTH - 1] = LinearCombination::constant(HashDomain::Node.tag(arity));
+    permute(cs, instance, &mut state);
+    state.into_iter().next().unwrap()
+}
+
+/// Same as [`crate::poseidon::compress`].
+pub fn compress(cs: &mut ConstraintSystem, input: &[LinearCombination]) -> LinearCombination {
+    let mut state: [LinearCombination; 16] = Default::default();
+    if input.len() <= 16 {
+        state[..input.len()].clone_from_slice(input);
+    } else {
+        let chunk = 1 << (4 * ((input.len() - 1).ilog2() / 4));
+        for (s, chunk) in state.iter_mut().zip(input.chunks(chunk)) {
+            *s = compress(cs, chunk);
+        }
+    }
+    permute_16(cs, &mut state);
+    let [first, ..] = state;
+    first
+}
+
+/// Same as [`crate::poseidon::compress_with_domain`].
+pub fn compress_with_domain(
+    cs: &mut ConstraintSystem,
+    domain: HashDomain,
+    input: &[LinearCombination],
+) -> LinearCombination {
+    let mut state: [LinearCombination; 16] = Default::default();
+    if input.len() <= 15 {
+        state[..input.len()].clone_from_slice(input);
+    } else {
+        let mut chunk = 15;
+        while chunk * 15 < input.len() {
+            chunk *= 15;
+        }
+        for (s, chunk) in state.iter_mut().zip(input.chunks(chunk)) {
+            *s = compress_with_domain(cs, domain, chunk);
+        }
+    }
+    state[15] = LinearCombination::constant(domain.tag(input.len()));
+    permute_16(cs, &mut state);
+    let [first, ..] = state;
+    first
+}
+
+fn full_round<const WIDTH: usize>(
+    cs: &mut ConstraintSystem,
+    external: &[[Fr; WIDTH]; WIDTH],
+    state: &mut [LinearCombination; WIDTH],
+    rc: &[Fr; WIDTH],
+) {
+    for (x, rc) in state.iter_mut().zip(rc) {
+        *x = sbox(cs, &x.add_constant(*rc));
+    }
+    *state = apply(external, state);
+}
+
+/// Computes $x^5$.
+fn sbox(cs: &mut ConstraintSystem, x: &LinearCombination) -> LinearCombination {
+    let x2 = cs.mul(x, x);
+    let x4 = cs.mul(&x2, &x2);
+    cs.mul(&x4, x)
+}
+
+/// Columns of a linear map, from its images of the unit vectors.
+fn matrix<const WIDTH: usize>(map: impl Fn(&mut [Fr; WIDTH])) -> [[Fr; WIDTH]; WIDTH] {
+    array::from_fn(|j| {
+        let mut column = [Fr::ZERO; WIDTH];
+        column[j] = Fr::ONE;
+        map(&mut column);
+        column
+    })
+}
+
+fn apply<const WIDTH: usize>(
+    columns: &[[Fr; WIDTH]; WIDTH],
+    state: &[LinearCombination; WIDTH],
+) -> [LinearCombination; WIDTH] {
+    array::from_fn(|i| LinearCombination::sum(columns.iter().map(|c| c[i]).zip(state)))
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{
+            merkle_tree::{MerkleHash, MerkleTree, Poseidon2Hash, ARITIES},
+            poseidon,
+        },
+        rand::{Rng, SeedableRng},
+        rand_chacha::ChaCha20Rng,
+    };
+
+    fn alloc<const N: usize>(
+        cs: &mut ConstraintSystem,
+        values: &[Fr; N],
+    ) -> [LinearCombination; N] {
+        array::from_fn(|i| cs.alloc(values[i]))
+    }
+
+    #[test]
+    fn test_permute_3() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let mut native: [Fr; 3] = array::from_fn(|_| rng.gen());
+        let mut cs = ConstraintSystem::new();
+        let mut state = alloc(&mut cs, &native);
+        permute_3(&mut cs, &mut state);
+        poseidon::permute_3(&mut native);
+        assert_eq!(state.each_ref().map(|x| cs.value(x)), native);
+        assert_eq!(cs.num_constraints(), 3 * (8 * 3 + 56));
+        let (r1cs, mut z) = cs.finish();
+        assert!(r1cs.is_satisfied(&z));
+        z[10] += Fr::ONE;
+        assert!(!r1cs.is_satisfied(&z));
+    }
+
+    #[test]
+    fn test_permute_16() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let mut native: [Fr; 16] = array::from_fn(|_| rng.gen());
+        let mut cs = ConstraintSystem::new();
+        let mut state = alloc(&mut cs, &native);
+        permute_16(&mut cs, &mut state);
+        poseidon::permute_16(&mut native);
+        assert_eq!(state.each_ref().map(|x| cs.value(x)), native);
+        assert_eq!(cs.num_constraints(), 3 * (8 * 16 + 57));
+        let (r1cs, z) = cs.finish();
+        assert!(r1cs.is_satisfied(&z));
+    }
+
+    #[test]
+    fn test_compress() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for size in [1, 16, 17, 100, 300] {
+            let native: Vec<Fr> = (0..size).map(|_| rng.gen()).collect();
+            let mut cs = ConstraintSystem::new();
+            let input: Vec<_> = native.iter().map(|x| cs.alloc(*x)).collect();
+            let output = compress(&mut cs, &input);
+            assert_eq!(cs.value(&output), poseidon::compress(&native));
+            let (r1cs, z) = cs.finish();
+            assert!(r1cs.is_satisfied(&z));
+        }
+    }
+
+    #[test]
+    fn test_compress_with_domain() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for size in [0, 15, 16, 226] {
+            let native: Vec<Fr> = (0..size).map(|_| rng.gen()).collect();
+            let mut cs = ConstraintSystem::new();
+            let input: Vec<_> = native.iter().map(|x| cs.alloc(*x)).collect();
+            let output = compress_with_domain(&mut cs, HashDomain::Column, &input);
+            let expected = poseidon::compress_with_domain(HashDomain::Column, &native);
+            assert_eq!(cs.value(&output), expected);
+            let (r1cs, z) = cs.finish();
+            assert!(r1cs.is_satisfied(&z));
+        }
+    }
+
+    #[test]
+    fn test_permute_24() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let mut native: [Fr; 24] = array::from_fn(|_| rng.gen());
+        let mut cs = ConstraintSystem::new();
+        let mut state = alloc(&mut cs, &native);
+        permute_24(&mut cs, &mut state);
+        poseidon::permute_24(&mut native);
+        assert_eq!(state.each_ref().map(|x| cs.value(x)), native);
+        assert_eq!(cs.num_constraints(), 3 * (8 * 24 + 57));
+        let (r1cs, z) = cs.finish();
+        assert!(r1cs.is_satisfied(&z));
+    }
+
+    #[test]
+    fn test_compress_node() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for arity in ARITIES {
+            // Full and zero padded families.
+            for size in [arity, arity - 1] {
+                let native: Vec<Fr> = (0..size).map(|_| rng.gen()).collect();
+                let mut cs = ConstraintSystem::new();
+                let family: Vec<_> = native.iter().map(|x| cs.alloc(*x)).collect();
+                let output = compress_node(&mut cs, arity, &family);
+                assert_eq!(cs.value(&output), Poseidon2Hash::compress(arity, &native));
+                let (r1cs, z) = cs.finish();
+                assert!(r1cs.is_satisfied(&z));
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_root() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let leaves: Vec<Fr> = (0..40).map(|_| rng.gen()).collect();
+        let tree = MerkleTree::new(leaves.clone());
+        let arity = tree.config().arity;
+        let mut cs = ConstraintSystem::new();
+        let mut layer: Vec<_> = leaves
+            .iter()
+            .map(|x| {
+                let x = cs.alloc(*x);
+                compress_with_domain(&mut cs, HashDomain::Leaf, &[x])
+            })
+            .collect();
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(arity)
+                .map(|family| compress_node(&mut cs, arity, family))
+                .collect();
+        }
+        assert_eq!(cs.value(&layer[0]), tree.root());
+        let (r1cs, z) = cs.finish();
+        assert!(r1cs.is_satisfied(&z));
+    }
+}
@@ -0,0 +1,154 @@
+//! Gadget for the transcript sponge, see [`crate::transcript::Sponge`].
+use {
+    super::{poseidon::permute_3, ConstraintSystem, LinearCombination},
+    crate::transcript::{label_value, DomainSeparator, INITIAL_STATE},
+};
+
+pub struct Sponge {
+    state: [LinearCombination; 3],
+    sponge: SpongeState,
+}
+
+// Sponge with rate 2 and capacity 1
+enum SpongeState {
+    Initial,
+    Absorbing,
+    Squeezing,
+    Full,
+}
+
+impl Sponge {
+    pub fn new() -> Self {
+        Self {
+            state: INITIAL_STATE.map(LinearCombination::constant),
+            sponge: SpongeState::Initial,
+        }
+    }
+
+    /// Same as [`crate::transcript::Prover::with_domain`], the IV is a constant.
+    pub fn with_domain(separator: &DomainSeparator) -> Self {
+        let mut state = INITIAL_STATE;
+        state[2] += separator.iv();
+        Self {
+            state: state.map(LinearCombination::constant),
+            sponge: SpongeState::Full,
+        }
+    }
+
+    /// Add the constant [`label_value`] to the capacity, same as the labeled
+    /// transcript methods.
+    pub fn absorb_label(&mut self, label: &str) {
+        self.state[2] = &self.state[2] + &LinearCombination::constant(label_value(label));
+        self.sponge = SpongeState::Full;
+    }
+
+    pub fn absorb(&mut self, cs: &mut ConstraintSystem, value: &LinearCombination) {
+        match self.sponge {
+            SpongeState::Initial => {
+                self.state[0] = &self.state[0] + value;
+                self.sponge = SpongeState::Absorbing;
+            }
+            SpongeState::Absorbing => {
+                self.state[1] = &self.state[1] + value;
+                self.sponge = SpongeState::Full;
+            }
+            SpongeState::Full | SpongeState::Squeezing => {
+                permute_3(cs, &mut self.state);
+                self.state[0] = &self.state[0] + value;
+                self.sponge = SpongeState::Absorbing;
+            }
+        }
+    }
+
+    pub fn squeeze(&mut self, cs: &mut ConstraintSystem) -> LinearCombination {
+        match self.sponge {
+            SpongeState::Initial => {
+                self.sponge = SpongeState::Squeezing;
+                self.state[0].clone()
+            }
+            SpongeState::Squeezing => {
+                self.sponge = SpongeState::Full;
+                self.state[1].clone()
+            }
+            SpongeState::Full | SpongeState::Absorbing => {
+                permute_3(cs, &mut self.state);
+                self.sponge = SpongeState::Squeezing;
+                self.state[0].clone()
+            }
+        }
+    }
+}
+
+impl Default for Sponge {
+    fn default() -> Self {
+        Sponge::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::transcript::{self, Prover, Verifier},
+        ark_bn254::Fr,
+        rand::{Rng, SeedableRng},
+        rand_chacha::ChaCha20Rng,
+    };
+
+    #[test]
+    fn test_sponge() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let mut native = transcript::Sponge::new();
+        let mut cs = ConstraintSystem::new();
+        let mut sponge = Sponge::new();
+        // Mixed absorb and squeeze runs exercise all state transitions.
+        for run in [1, 3, 2, 1, 4] {
+            for _ in 0..run {
+                let value: Fr = rng.gen();
+                native.absorb(value);
+                let value = cs.alloc(value);
+                sponge.absorb(&mut cs, &value);
+            }
+            for _ in 0..run {
+                let challenge = sponge.squeeze(&mut cs);
+                assert_eq!(cs.value(&challenge), native.squeeze());
+            }
+        }
+        let (r1cs, z) = cs.finish();
+        assert!(r1cs.is_satisfied(&z));
+    }
+
+    #[test]
+    fn test_domain_and_labels() {
+        let separator = DomainSeparator::new("sponge test", 1, &[Fr::from(3), Fr::from(4)]);
+        let proof = {
+            let mut transcript: Prover = Prover::with_domain(&separator);
+            transcript.write_labeled("first", Fr::from(5));
+            transcript.read_labeled("challenge");
+            transcript.write(Fr::from(6));
+            transcript.finish()
+        };
+        let mut native: Verifier = Verifier::with_domain(&separator, &proof);
+        let mut cs = ConstraintSystem::new();
+        let mut sponge = Sponge::with_domain(&separator);
+
+        let value = native.read_labeled("first");
+        sponge.absorb_label("first");
+        let value = cs.alloc(value);
+        sponge.absorb(&mut cs, &value);
+
+        let expected = native.generate_labeled("challenge");
+        sponge.absorb_label("challenge");
+        let challenge = sponge.squeeze(&mut cs);
+        assert_eq!(cs.value(&challenge), expected);
+
+        let value = native.read();
+        let value = cs.alloc(value);
+        sponge.absorb(&mut cs, &value);
+        let challenge = sponge.squeeze(&mut cs);
+        assert_eq!(cs.value(&challenge), native.generate());
+
+        let (r1cs, z) = cs.finish();
+        assert!(r1cs.is_satisfied(&z));
+    }
+}
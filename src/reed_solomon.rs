@@ -1,40 +1,439 @@
 use {
-    crate::ntt::{intt, ntt},
+    crate::{
+        linear_code::LinearCode,
+        ntt::{root, transpose, NttPlan, GENERATOR},
+        polynomial::DensePolynomial,
+    },
     ark_bn254::Fr,
     ark_ff::Field,
+    std::{iter::successors, sync::OnceLock},
 };
 
-/// Reed-Solomon encoding.
-/// Rate is `x.len()/e.len()`.
-pub fn encode(m: &mut [Fr], c: &mut [Fr]) {
-    // TODO: Support arbitrary input and output length.
+/// Evaluation domain $c ⋅ ⟨ω⟩$, a coset of the subgroup of order `size`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Domain {
+    pub size: usize,
+    pub offset: Fr,
+}
+
+/// Reed-Solomon code of polynomials with `message_length` coefficients
+/// evaluated on a [`Domain`].
+///
+/// When `message_length` divides the domain size the code can also be used
+/// in systematic form, where the message appears verbatim in the codeword.
+pub struct ReedSolomon {
+    message_length: usize,
+    domain: Domain,
+    plan: NttPlan,
+    /// Built on first use by [`Self::encode_systematic`].
+    systematic_plan: OnceLock<NttPlan>,
+}
+
+impl Domain {
+    pub fn new(size: usize, offset: Fr) -> Self {
+        assert!(
+            root(size).is_some(),
+            "Domain size must divide the group order."
+        );
+        assert_ne!(offset, Fr::ZERO, "Domain offset must be non-zero.");
+        Self { size, offset }
+    }
+
+    /// Generator $ω$ of the subgroup.
+    pub fn generator(&self) -> Fr {
+        root(self.size).unwrap()
+    }
+
+    /// The `i`-th element $c ⋅ ω^i$.
+    pub fn element(&self, i: usize) -> Fr {
+        self.offset * self.generator().pow([i as u64])
+    }
+
+    /// All elements in order.
+    pub fn elements(&self) -> impl Iterator<Item = Fr> {
+        let generator = self.generator();
+        successors(Some(self.offset), move |x| Some(*x * generator)).take(self.size)
+    }
+
+    /// The image of the domain under $X ↦ X^F$.
+    pub fn fold(&self, factor: usize) -> Self {
+        assert!(
+            self.size.is_multiple_of(factor),
+            "Folding factor must divide domain size."
+        );
+        Self::new(self.size / factor, self.offset.pow([factor as u64]))
+    }
+
+    /// Positions of the `factor` elements that map to the same element as `index`
+    /// under $X ↦ X^F$. These are `index mod n / F` plus multiples of `n / F`.
+    pub fn fold_indices(&self, factor: usize, index: usize) -> impl Iterator<Item = usize> {
+        let stride = self.size / factor;
+        (0..factor).map(move |j| index % stride + j * stride)
+    }
+}
+
+impl ReedSolomon {
+    /// Code evaluated on the coset $5 ⋅ ⟨ω⟩$.
+    pub fn new(message_length: usize, codeword_length: usize) -> Self {
+        Self::with_domain(message_length, Domain::new(codeword_length, GENERATOR))
+    }
+
+    pub fn with_domain(message_length: usize, domain: Domain) -> Self {
+        assert!(
+            message_length <= domain.size,
+            "Codeword can not be shorter than message."
+        );
+        Self {
+            message_length,
+            domain,
+            plan: NttPlan::new(domain.size),
+            systematic_plan: OnceLock::new(),
+        }
+    }
+
+    pub fn message_length(&self) -> usize {
+        self.message_length
+    }
+
+    pub fn codeword_length(&self) -> usize {
+        self.domain.size
+    }
+
+    pub fn domain(&self) -> Domain {
+        self.domain
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.message_length as f64 / self.domain.size as f64
+    }
+
+    /// Minimum Hamming distance between distinct codewords.
+    pub fn distance(&self) -> usize {
+        self.domain.size - self.message_length + 1
+    }
+
+    /// Encodes the polynomial with coefficients `message`.
+    pub fn encode(&self, message: &[Fr], codeword: &mut [Fr]) {
+        assert_eq!(message.len(), self.message_length);
+        assert_eq!(codeword.len(), self.domain.size);
+        // Move to the coset. P(X) -> P(c ⋅ X)
+        codeword.fill(Fr::ZERO);
+        let mut offset_i = Fr::ONE;
+        for (c, m) in codeword.iter_mut().zip(message) {
+            *c = offset_i * m;
+            offset_i *= self.domain.offset;
+        }
+        self.plan.ntt(codeword);
+    }
 
-    // Convert x to coefficients.
-    // TODO: Is this necessary?
-    intt(m);
+    /// Decodes a word with at most $⌊(n - k) / 2⌋$ errors using Gao's algorithm.
+    /// Returns the message, or `None` if the word is too far from the code.
+    pub fn decode(&self, word: &[Fr]) -> Option<Vec<Fr>> {
+        assert_eq!(word.len(), self.domain.size);
+        let vanishing = DensePolynomial::vanishing(self.domain.size, self.domain.offset);
+        let interpolant = DensePolynomial::interpolate_coset(word, self.domain.offset);
+        self.gao(self.domain.size, vanishing, interpolant)
+    }
 
-    // Compute coset evaluations
-    let mut current_coset = Fr::ONE;
-    for c in c.chunks_exact_mut(m.len()) {
-        // Move to a different coset. P(X) -> P(c * X)
-        let coset = Fr::from(5);
-        let mut coset_i = coset;
-        for m in m.iter_mut().skip(1) {
-            *m *= coset_i;
-            coset_i *= coset;
+    /// Decodes a word with erased symbols set to `None`. With `s` erasures it
+    /// corrects up to $⌊(n - s - k) / 2⌋$ additional errors.
+    pub fn decode_erasures(&self, word: &[Option<Fr>]) -> Option<Vec<Fr>> {
+        assert_eq!(word.len(), self.domain.size);
+        let (points, values): (Vec<Fr>, Vec<Fr>) = self
+            .domain
+            .elements()
+            .zip(word)
+            .filter_map(|(x, y)| y.map(|y| (x, y)))
+            .unzip();
+        if points.len() < self.message_length {
+            return None;
         }
+        let vanishing = DensePolynomial::from_roots(&points);
+        let interpolant = DensePolynomial::interpolate(&points, &values);
+        self.gao(points.len(), vanishing, interpolant)
+    }
+
+    /// Hamming distance from `word` to the nearest codeword, or `None` if it
+    /// is beyond the unique decoding radius.
+    pub fn distance_to_code(&self, word: &[Fr]) -> Option<usize> {
+        let message = self.decode(word)?;
+        let mut codeword = vec![Fr::ZERO; self.domain.size];
+        self.encode(&message, &mut codeword);
+        Some(word.iter().zip(&codeword).filter(|(a, b)| a != b).count())
+    }
+
+    /// Gao's algorithm on `n` points with vanishing polynomial $g_0$ and
+    /// interpolant $g_1$ of the received values.
+    fn gao(
+        &self,
+        n: usize,
+        vanishing: DensePolynomial,
+        interpolant: DensePolynomial,
+    ) -> Option<Vec<Fr>> {
+        // Partial extended Euclidean algorithm until deg(g) < (n + k) / 2.
+        let done = |g: &DensePolynomial| 2 * g.degree() < n + self.message_length || g.is_zero();
+        let (mut g_prev, mut g) = (vanishing, interpolant);
+        let (mut v_prev, mut v) = (DensePolynomial::zero(), DensePolynomial::new(vec![Fr::ONE]));
+        while !done(&g) {
+            let (q, r) = g_prev.div_rem(&g);
+            let v_next = &v_prev - &(&q * &v);
+            (g_prev, g) = (g, r);
+            (v_prev, v) = (v, v_next);
+        }
+        // The error locator v divides g, and the quotient is the message.
+        let (message, remainder) = g.div_rem(&v);
+        if !remainder.is_zero() || message.coefficients().len() > self.message_length {
+            return None;
+        }
+        let mut message = message.coefficients().to_vec();
+        message.resize(self.message_length, Fr::ZERO);
+        Some(message)
+    }
+
+    /// Position of the `i`-th message symbol in a systematic codeword.
+    pub fn systematic_index(&self, i: usize) -> usize {
+        assert!(i < self.message_length);
+        i * (self.domain.size / self.message_length)
+    }
 
-        // Apply coset factors
-        c.copy_from_slice(m);
-        ntt(c);
+    /// Systematic encoding, `codeword[systematic_index(i)] == message[i]`.
+    ///
+    /// The message is interpreted as evaluations on the coset $c ⋅ ⟨ω^{n / k}⟩$,
+    /// which is the subset of the domain at the systematic positions.
+    pub fn encode_systematic(&self, message: &[Fr], codeword: &mut [Fr]) {
+        assert!(
+            self.domain.size.is_multiple_of(self.message_length),
+            "Message length must divide codeword length for systematic encoding."
+        );
+        let plan = self
+            .systematic_plan
+            .get_or_init(|| NttPlan::new(self.message_length));
+        assert_eq!(message.len(), self.message_length);
+        assert_eq!(codeword.len(), self.domain.size);
+        // Interpolate the message on the coset to obtain coefficients of P(c ⋅ X).
+        codeword.fill(Fr::ZERO);
+        let coefficients = &mut codeword[..self.message_length];
+        coefficients.copy_from_slice(message);
+        plan.intt(coefficients);
+        self.plan.ntt(codeword);
     }
 }
 
-/// Fold the codeword using a random factor.
-pub fn fold(c: &mut [Fr], degree: usize, r: &Fr) {
-    assert_eq!(degree, 2);
-    assert_eq!(c.len() % degree, 0);
+impl LinearCode for ReedSolomon {
+    fn message_length(&self) -> usize {
+        self.message_length()
+    }
+
+    fn codeword_length(&self) -> usize {
+        self.codeword_length()
+    }
+
+    fn distance(&self) -> usize {
+        self.distance()
+    }
+
+    fn systematic_index(&self, i: usize) -> usize {
+        self.systematic_index(i)
+    }
+
+    fn encode_systematic(&self, message: &[Fr], codeword: &mut [Fr]) {
+        self.encode_systematic(message, codeword)
+    }
+}
+
+/// Reed-Solomon encoding of the polynomial with coefficients `m` on the coset
+/// $5 ⋅ ⟨ω⟩$ of order `c.len()`, same as [`ReedSolomon::new`]. Rate is
+/// `m.len()/c.len()`. This builds single-use plans, for repeated encodings
+/// create a [`ReedSolomon`] instead.
+///
+/// Panics if `c.len()` does not divide the group order, see
+/// [`crate::ntt::smooth_size`]. Use [`crate::ntt::chirp_z`] to evaluate on
+/// other geometric sequences.
+pub fn encode(m: &[Fr], c: &mut [Fr]) {
+    assert!(
+        root(c.len()).is_some(),
+        "Codeword length must divide the group order."
+    );
+    ReedSolomon::new(m.len(), c.len()).encode(m, c);
+}
+
+/// Folds a codeword on `domain` by `factor = plan.size()` using the random
+/// value `r`. The plan can be reused across rounds and queries.
+///
+/// Writing $f(X) = \sum_{j < F} X^j ⋅ f_j(X^F)$, the result is the codeword of
+/// $g(Y) = \sum_{j < F} r^j ⋅ f_j(Y)$ on `domain.fold(factor)`. This maps codewords
+/// of degree $< k$ to codewords of degree $< k / F$.
+pub fn fold(domain: Domain, plan: &NttPlan, codeword: &[Fr], r: Fr) -> Vec<Fr> {
+    assert_eq!(codeword.len(), domain.size);
+    let factor = plan.size();
+    let folded = domain.fold(factor);
+
+    // Gather the fibres of X ↦ X^F and recover x^j ⋅ f_j(x^F) for each.
+    let mut values = codeword.to_vec();
+    transpose(&mut values, factor, folded.size);
+    plan.intt(&mut values);
 
-    let (a, b) = c.split_at_mut(c.len() / 2);
-    a.iter_mut().zip(b.iter()).for_each(|(a, b)| *a += r * b);
+    // Combine using powers of r / x.
+    let generator_inv = domain.generator().inverse().unwrap();
+    let mut x_inv = domain.offset.inverse().unwrap();
+    values
+        .chunks_exact(factor)
+        .map(|coefficients| {
+            let z = r * x_inv;
+            x_inv *= generator_inv;
+            horner(coefficients, z)
+        })
+        .collect()
+}
+
+/// Verifier side of [`fold`] for a single position.
+/// Takes the codeword values at `domain.fold_indices(factor, index)` and
+/// returns the folded codeword at `index mod n / F`.
+pub fn fold_query(domain: Domain, plan: &NttPlan, index: usize, values: &[Fr], r: Fr) -> Fr {
+    let factor = plan.size();
+    assert_eq!(values.len(), factor);
+    let x = domain.element(index % (domain.size / factor));
+    let mut coefficients = values.to_vec();
+    plan.intt(&mut coefficients);
+    horner(&coefficients, r * x.inverse().unwrap())
+}
+
+fn horner(coefficients: &[Fr], x: Fr) -> Fr {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Fr::ZERO, |acc, c| acc * x + c)
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::polynomial::DensePolynomial,
+        rand::{Rng, SeedableRng},
+        rand_chacha::ChaCha20Rng,
+    };
+
+    #[test]
+    fn test_encode() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for (k, n) in [(4, 16), (10, 24), (13, 18), (3, 12), (9, 9), (7, 144)] {
+            let m: Vec<Fr> = (0..k).map(|_| rng.gen()).collect();
+            let mut c = vec![Fr::ZERO; n];
+            encode(&m, &mut c);
+            let ratio = root(n).unwrap();
+            for (i, c) in c.iter().enumerate() {
+                let x = GENERATOR * ratio.pow([i as u64]);
+                assert_eq!(*c, m.iter().rev().fold(Fr::ZERO, |acc, m| acc * x + m));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Codeword length must divide the group order.")]
+    fn test_encode_unsupported_length() {
+        encode(&[Fr::ONE; 3], &mut [Fr::ZERO; 10]);
+    }
+
+    #[test]
+    fn test_low_degree() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for (k, n) in [(1, 1), (4, 16), (10, 24), (100, 377), (256, 1024)] {
+            let code = ReedSolomon::with_domain(k, Domain::new(n, rng.gen()));
+            assert_eq!(code.distance(), n - k + 1);
+            assert_eq!(code.rate(), k as f64 / n as f64);
+            let m: Vec<Fr> = (0..k).map(|_| rng.gen()).collect();
+            let mut c = vec![Fr::ZERO; n];
+            code.encode(&m, &mut c);
+            let p = DensePolynomial::interpolate_coset(&c, code.domain().offset);
+            assert!(p.coefficients().len() <= k);
+            assert_eq!(p, DensePolynomial::new(m));
+            let i = rng.gen_range(0..n);
+            assert_eq!(p.evaluate(code.domain().element(i)), c[i]);
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for (k, n) in [(1, 4), (4, 16), (10, 24), (13, 26), (30, 64)] {
+            let code = ReedSolomon::new(k, n);
+            let m: Vec<Fr> = (0..k).map(|_| rng.gen()).collect();
+            let mut c = vec![Fr::ZERO; n];
+            code.encode(&m, &mut c);
+
+            // Errors up to the unique decoding radius.
+            let errors = (n - k) / 2;
+            let mut word = c.clone();
+            for i in 0..errors {
+                word[(i * 7) % n] += Fr::from(1 + i as u64);
+            }
+            assert_eq!(code.decode(&word), Some(m.clone()));
+            assert_eq!(code.distance_to_code(&word), Some(errors));
+
+            // Erasures trade off against errors.
+            let erasures = (n - k) / 2;
+            let errors = (n - k - erasures) / 2;
+            let mut word: Vec<Option<Fr>> = c.iter().copied().map(Some).collect();
+            for i in 0..erasures {
+                word[n - 1 - i] = None;
+            }
+            for w in word.iter_mut().take(errors) {
+                *w.as_mut().unwrap() += Fr::ONE;
+            }
+            assert_eq!(code.decode_erasures(&word), Some(m.clone()));
+
+            // A word far from the code is rejected.
+            let word: Vec<Fr> = (0..n).map(|_| rng.gen()).collect();
+            assert_eq!(code.distance_to_code(&word), None);
+        }
+    }
+
+    #[test]
+    fn test_fold() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for factor in [2, 4, 8, 16] {
+            let (k, n) = (64, 256);
+            let domain = Domain::new(n, rng.gen());
+            let code = ReedSolomon::with_domain(k, domain);
+            let m: Vec<Fr> = (0..k).map(|_| rng.gen()).collect();
+            let mut c = vec![Fr::ZERO; n];
+            code.encode(&m, &mut c);
+
+            let r: Fr = rng.gen();
+            let plan = NttPlan::new(factor);
+            let folded = fold(domain, &plan, &c, r);
+
+            // Folded codeword encodes the folded coefficients.
+            let fm: Vec<Fr> = m.chunks_exact(factor).map(|m| horner(m, r)).collect();
+            let mut expected = vec![Fr::ZERO; n / factor];
+            ReedSolomon::with_domain(k / factor, domain.fold(factor)).encode(&fm, &mut expected);
+            assert_eq!(folded, expected);
+
+            // Single queries agree with the folded codeword.
+            for index in [0, 1, n / factor - 1, n / factor + 3, n - 1] {
+                let values: Vec<Fr> = domain.fold_indices(factor, index).map(|i| c[i]).collect();
+                assert_eq!(
+                    fold_query(domain, &plan, index, &values, r),
+                    folded[index % (n / factor)]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_systematic() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for (k, n) in [(1, 1), (4, 16), (8, 24), (13, 26), (256, 1024)] {
+            let code = ReedSolomon::with_domain(k, Domain::new(n, rng.gen()));
+            let m: Vec<Fr> = (0..k).map(|_| rng.gen()).collect();
+            let mut c = vec![Fr::ZERO; n];
+            code.encode_systematic(&m, &mut c);
+            for (i, m) in m.iter().enumerate() {
+                assert_eq!(c[code.systematic_index(i)], *m);
+            }
+            let p = DensePolynomial::interpolate_coset(&c, code.domain().offset);
+            assert!(p.coefficients().len() <= k);
+        }
+    }
 }
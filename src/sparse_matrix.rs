@@ -1,4 +1,4 @@
-use ark_bn254::Fr;
+use {ark_bn254::Fr, ark_ff::Field};
 
 pub struct SparseMatrix {
     pub rows: usize,
@@ -10,9 +10,9 @@ impl SparseMatrix {
     /// Compute Self ⋅ v
     pub fn mul_left(&self, v: &[Fr]) -> Vec<Fr> {
         assert_eq!(v.len(), self.cols);
-        let mut res = vec![Fr::zero(); self.rows];
+        let mut res = vec![Fr::ZERO; self.rows];
         for (i, val) in &self.entries {
-            res[i / self.cols] += val * v[i % self.cols];
+            res[i / self.cols] += *val * v[i % self.cols];
         }
         res
     }
@@ -1,5 +1,5 @@
 use {
-    crate::{Prover, Verifier},
+    crate::transcript::{DomainSeparator, DuplexSponge, Prover, Verifier},
     ark_bn254::Fr,
     ark_ff::{MontFp, One, Zero},
     itertools::izip,
@@ -32,10 +32,16 @@ pub fn eval_mle(coefficients: &[Fr], eval: &[Fr]) -> Fr {
         let (c0, c1) = coefficients.split_at(coefficients.len() / 2);
         (Fr::one() - x) * eval_mle(c0, tail) + x * eval_mle(c1, tail)
     } else {
-        return coefficients[0];
+        coefficients[0]
     }
 }
 
+/// Domain separator for a sumcheck over `size` variables of degree `degree`.
+pub fn domain_separator(size: usize, degree: usize) -> DomainSeparator {
+    let parameters = [size, degree].map(|p| Fr::from(p as u64));
+    DomainSeparator::new("sumcheck", 1, &parameters)
+}
+
 /// Updates f(x, x') -> f(r, x') and returns f
 fn update(f: &mut [Fr], r: Fr) -> &mut [Fr] {
     let (a, b) = f.split_at_mut(f.len() / 2);
@@ -48,7 +54,7 @@ fn update(f: &mut [Fr], r: Fr) -> &mut [Fr] {
 // TODO: This is destructive on coefficients, but only overwrites first half.
 // We can restore the original requires n/2 space.
 pub fn prove_sumcheck(
-    transcript: &mut Prover,
+    transcript: &mut Prover<impl DuplexSponge>,
     size: usize,
     mut f: &mut [Fr],
     mut sum: Fr,
@@ -78,7 +84,7 @@ pub fn prove_sumcheck(
 /// Prove sumcheck for $\sum_x f(x) ⋅ g(x)$.
 /// Returns $(e, r)$ for reduced claim $e = f(r)⋅ g(r)$.
 pub fn prove_sumcheck_product(
-    transcript: &mut Prover,
+    transcript: &mut Prover<impl DuplexSponge>,
     size: usize,
     mut f: &mut [Fr],
     mut g: &mut [Fr],
@@ -123,7 +129,7 @@ pub fn prove_sumcheck_product(
 /// Sumcheck for $\sum_x e(x) ⋅ (a(x) ⋅ b(x) - c(x))$.
 /// Returns $(e, r)$ for reduced claim $e = e(r) ⋅ (a(r) ⋅ b(r) - c(r))$.
 pub fn prove_sumcheck_r1cs(
-    transcript: &mut Prover,
+    transcript: &mut Prover<impl DuplexSponge>,
     size: usize,
     mut e: &mut [Fr],
     mut a: &mut [Fr],
@@ -185,7 +191,7 @@ pub fn prove_sumcheck_r1cs(
 /// Verify sumcheck for $N$-degree polynomials.
 /// I.e. N = 1 for linear, 2 for quadratic, etc.
 pub fn verify_sumcheck<const N: usize>(
-    transcript: &mut Verifier,
+    transcript: &mut Verifier<impl DuplexSponge>,
     size: usize,
     mut e: Fr,
 ) -> (Fr, Vec<Fr>) {
@@ -251,7 +257,8 @@ mod test {
         let s = f.iter().sum();
 
         // Prove
-        let mut transcript = Prover::new();
+        let separator = domain_separator(size, 1);
+        let mut transcript: Prover = Prover::with_domain(&separator);
         transcript.write(s);
         let mut copy = f.clone();
         let (e, rs) = prove_sumcheck(&mut transcript, size, &mut copy, s);
@@ -260,7 +267,7 @@ mod test {
         dbg!(proof.len() * std::mem::size_of::<Fr>());
 
         // Verify
-        let mut transcript = Verifier::new(&proof);
+        let mut transcript: Verifier = Verifier::with_domain(&separator, &proof);
         let e = transcript.read();
         let (e, rs) = verify_sumcheck::<1>(&mut transcript, size, e);
         assert_eq!(eval_mle(&f, &rs), e);
@@ -275,7 +282,8 @@ mod test {
         let s = f.iter().zip(g.iter()).map(|(f, g)| f * g).sum();
 
         // Prove
-        let mut transcript = Prover::new();
+        let separator = domain_separator(size, 2);
+        let mut transcript: Prover = Prover::with_domain(&separator);
         transcript.write(s);
         let mut fc = f.clone();
         let mut gc = g.clone();
@@ -285,7 +293,7 @@ mod test {
         dbg!(proof.len() * std::mem::size_of::<Fr>());
 
         // Verify
-        let mut transcript = Verifier::new(&proof);
+        let mut transcript: Verifier = Verifier::with_domain(&separator, &proof);
         let vs = transcript.read();
         assert_eq!(vs, s);
         let (ve, vrs) = verify_sumcheck::<2>(&mut transcript, size, s);
@@ -306,7 +314,8 @@ mod test {
             .sum();
 
         // Prove
-        let mut transcript = Prover::new();
+        let separator = domain_separator(size, 3);
+        let mut transcript: Prover = Prover::with_domain(&separator);
         transcript.write(s);
         let mut ec = e.clone();
         let mut ac = a.clone();
@@ -322,7 +331,7 @@ mod test {
         dbg!(proof.len() * std::mem::size_of::<Fr>());
 
         // Verify
-        let mut transcript = Verifier::new(&proof);
+        let mut transcript: Verifier = Verifier::with_domain(&separator, &proof);
         let vs = transcript.read();
         assert_eq!(vs, s);
         let (ve, vrs) = verify_sumcheck::<3>(&mut transcript, size, s);
@@ -0,0 +1,117 @@
+use {
+    super::DuplexSponge,
+    crate::hash::{decode_uniform, encode, ByteHash},
+    ark_bn254::Fr,
+    std::marker::PhantomData,
+};
+
+/// Duplex sponge over a byte oriented hash.
+///
+/// Absorbed elements are buffered in their 32 byte encoding. Each squeeze
+/// updates the state to `H(state ‖ buffer)` and returns it as a field element,
+/// rehashing until the rejection sampling in [`decode_uniform`] accepts.
+/// An EVM verifier computes the same with `keccak256(abi.encodePacked(..))`.
+///
+/// The state starts as the encoded IV, or zero without one. A label updates
+/// the state to `H(state ‖ buffer ‖ 0x01 ‖ label)`, whose input length is odd
+/// unlike that of any squeeze.
+pub struct ByteSponge<H: ByteHash> {
+    state: [u8; 32],
+    buffer: Vec<u8>,
+    hash: PhantomData<H>,
+}
+
+impl<H: ByteHash> ByteSponge<H> {
+    pub fn new() -> Self {
+        Self {
+            state: [0; 32],
+            buffer: Vec::new(),
+            hash: PhantomData,
+        }
+    }
+}
+
+/// Separates a label from the buffered elements.
+const LABEL_MARKER: u8 = 0x01;
+
+impl<H: ByteHash> DuplexSponge for ByteSponge<H> {
+    fn with_iv(iv: Fr) -> Self {
+        Self {
+            state: encode(iv),
+            ..Self::new()
+        }
+    }
+
+    fn absorb(&mut self, value: Fr) {
+        self.buffer.extend_from_slice(&encode(value));
+    }
+
+    fn absorb_label(&mut self, label: Fr) {
+        let mut input = self.state.to_vec();
+        input.append(&mut self.buffer);
+        input.push(LABEL_MARKER);
+        input.extend_from_slice(&encode(label));
+        self.state = H::hash(&input);
+    }
+
+    fn squeeze(&mut self) -> Fr {
+        loop {
+            let mut input = self.state.to_vec();
+            input.append(&mut self.buffer);
+            self.state = H::hash(&input);
+            if let Some(value) = decode_uniform(self.state) {
+                return value;
+            }
+        }
+    }
+}
+
+impl<H: ByteHash> Default for ByteSponge<H> {
+    fn default() -> Self {
+        ByteSponge::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::hash::{Blake3, Keccak256, Sha256},
+        ark_ff::MontFp,
+    };
+
+    #[test]
+    fn test_sha256_vector() {
+        // Computed with Python's hashlib, exercises a rejection.
+        let mut sponge = ByteSponge::<Sha256>::new();
+        sponge.absorb(Fr::from(1));
+        sponge.absorb(Fr::from(2));
+        assert_eq!(
+            sponge.squeeze(),
+            MontFp!(
+                "11842649559037109873406644415243125614817883814696406282463952652324952292706"
+            )
+        );
+        assert_eq!(
+            sponge.squeeze(),
+            MontFp!("7400931767359339990277936779371314438569445162143836297541597583338548949205")
+        );
+    }
+
+    #[test]
+    fn test_distinct() {
+        fn squeeze<H: ByteHash>() -> Fr {
+            let mut sponge = ByteSponge::<H>::new();
+            sponge.absorb(Fr::from(1));
+            sponge.squeeze()
+        }
+        let values = [
+            squeeze::<Keccak256>(),
+            squeeze::<Sha256>(),
+            squeeze::<Blake3>(),
+        ];
+        assert_ne!(values[0], values[1]);
+        assert_ne!(values[1], values[2]);
+        assert_ne!(values[0], values[2]);
+    }
+}
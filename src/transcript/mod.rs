@@ -1,30 +1,173 @@
+mod byte_sponge;
 mod sponge;
 
-pub use sponge::Sponge;
 use {
+    crate::poseidon::{compress_with_domain, HashDomain},
     ark_bn254::{Fq, Fr, G1Affine},
     ark_ec::AffineRepr,
     ark_ff::PrimeField,
 };
+pub use {
+    byte_sponge::ByteSponge,
+    sponge::{Sponge, INITIAL_STATE},
+};
+
+/// Duplex sponge over field elements that drives the Fiat-Shamir transform.
+pub trait DuplexSponge: Default {
+    /// Sponge with `iv` in its initial state, where no absorb can reach it.
+    fn with_iv(iv: Fr) -> Self;
+
+    fn absorb(&mut self, value: Fr);
+
+    /// Absorb a label such that no sequence of [`Self::absorb`] has the same
+    /// effect, see [`label_value`].
+    fn absorb_label(&mut self, label: Fr);
+
+    /// Squeeze a uniform random field element.
+    fn squeeze(&mut self) -> Fr;
+}
+
+/// Domain separator set as the sponge IV when a transcript is created, so
+/// challenges of different protocols, versions or parameter sets are independent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DomainSeparator {
+    pub protocol: String,
+    pub version: u32,
+    pub parameters: Vec<Fr>,
+}
+
+impl DomainSeparator {
+    pub fn new(protocol: &str, version: u32, parameters: &[Fr]) -> Self {
+        Self {
+            protocol: protocol.to_string(),
+            version,
+            parameters: parameters.to_vec(),
+        }
+    }
 
-pub struct Prover {
-    sponge: Sponge,
+    /// Sponge IV, the [`HashDomain::Separator`] hash of the encoded separator.
+    pub fn iv(&self) -> Fr {
+        compress_with_domain(HashDomain::Separator, &self.elements().collect::<Vec<_>>())
+    }
+
+    fn elements(&self) -> impl Iterator<Item = Fr> + '_ {
+        byte_elements(self.protocol.as_bytes())
+            .chain([
+                Fr::from(self.version),
+                Fr::from(self.parameters.len() as u64),
+            ])
+            .chain(self.parameters.iter().copied())
+    }
+}
+
+/// The [`HashDomain::Label`] hash of `label`, passed to
+/// [`DuplexSponge::absorb_label`].
+pub fn label_value(label: &str) -> Fr {
+    compress_with_domain(
+        HashDomain::Label,
+        &byte_elements(label.as_bytes()).collect::<Vec<_>>(),
+    )
+}
+
+/// The length of `bytes` followed by 31 byte big-endian chunks, which always
+/// fit the field.
+fn byte_elements(bytes: &[u8]) -> impl Iterator<Item = Fr> + '_ {
+    std::iter::once(Fr::from(bytes.len() as u64))
+        .chain(bytes.chunks(31).map(Fr::from_be_bytes_mod_order))
+}
+
+/// Prover transcript, by default over the Poseidon2 [`Sponge`]. Use
+/// [`ByteSponge`] to target native or EVM verifiers.
+pub struct Prover<S: DuplexSponge = Sponge> {
+    sponge: S,
+    bits: Bits,
     pub proof: Vec<Fr>,
 }
 
-pub struct Verifier<'a> {
-    sponge: Sponge,
+pub struct Verifier<'a, S: DuplexSponge = Sponge> {
+    sponge: S,
+    bits: Bits,
     proof: &'a [Fr],
 }
 
+/// Unused challenge bits, so that small challenges share a squeeze. Emptied
+/// whenever the sponge absorbs, so challenges depend on all prior messages.
+#[derive(Default)]
+struct Bits {
+    value: u128,
+    count: u32,
+}
+
+impl Bits {
+    /// Uniform `count ≤ 64` bit integer. Each squeeze provides the low 128 bits
+    /// of a uniform field element, which are within $2^{-125}$ of uniform.
+    fn take(&mut self, sponge: &mut impl DuplexSponge, count: u32) -> u64 {
+        assert!(count <= 64);
+        if self.count < count {
+            let limbs = sponge.squeeze().into_bigint().0;
+            self.value = u128::from(limbs[0]) | (u128::from(limbs[1]) << 64);
+            self.count = 128;
+        }
+        let result = (self.value & ((1 << count) - 1)) as u64;
+        self.value >>= count;
+        self.count -= count;
+        result
+    }
+
+    /// Uniform integer in `[0, n)` by rejection sampling.
+    fn integer(&mut self, sponge: &mut impl DuplexSponge, n: usize) -> usize {
+        assert!(n > 0, "Empty range.");
+        let count = usize::BITS - (n - 1).leading_zeros();
+        loop {
+            let value = self.take(sponge, count) as usize;
+            if value < n {
+                return value;
+            }
+        }
+    }
+
+    fn bits(&mut self, sponge: &mut impl DuplexSponge, count: usize) -> Vec<bool> {
+        (0..count).map(|_| self.take(sponge, 1) == 1).collect()
+    }
+
+    /// Sorted set of `count` distinct uniform indices in `[0, n)`.
+    fn indices(&mut self, sponge: &mut impl DuplexSponge, n: usize, count: usize) -> Vec<usize> {
+        assert!(count <= n, "Not enough distinct indices.");
+        let mut indices = Vec::with_capacity(count);
+        while indices.len() < count {
+            let index = self.integer(sponge, n);
+            if let Err(position) = indices.binary_search(&index) {
+                indices.insert(position, index);
+            }
+        }
+        indices
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
 impl Prover {
     pub fn new() -> Self {
+        Self::with_sponge(Sponge::new())
+    }
+}
+
+impl<S: DuplexSponge> Prover<S> {
+    pub fn with_sponge(sponge: S) -> Self {
         Self {
-            sponge: Sponge::new(),
+            sponge,
+            bits: Bits::default(),
             proof: Vec::new(),
         }
     }
 
+    /// Transcript with the [`DomainSeparator::iv`] of `separator`.
+    pub fn with_domain(separator: &DomainSeparator) -> Self {
+        Self::with_sponge(S::with_iv(separator.iv()))
+    }
+
     pub fn finish(self) -> Vec<Fr> {
         self.proof
     }
@@ -33,11 +176,40 @@ impl Prover {
         self.sponge.squeeze()
     }
 
+    /// Uniform integer in `[0, n)`.
+    pub fn read_integer(&mut self, n: usize) -> usize {
+        self.bits.integer(&mut self.sponge, n)
+    }
+
+    pub fn read_bits(&mut self, count: usize) -> Vec<bool> {
+        self.bits.bits(&mut self.sponge, count)
+    }
+
+    /// Sorted set of `count` distinct uniform indices in `[0, n)`.
+    pub fn read_indices(&mut self, n: usize, count: usize) -> Vec<usize> {
+        self.bits.indices(&mut self.sponge, n, count)
+    }
+
     pub fn write(&mut self, value: Fr) {
+        self.bits.clear();
         self.sponge.absorb(value);
         self.proof.push(value);
     }
 
+    /// Same as [`Self::read`], with `label` absorbed first.
+    pub fn read_labeled(&mut self, label: &str) -> Fr {
+        self.bits.clear();
+        self.sponge.absorb_label(label_value(label));
+        self.read()
+    }
+
+    /// Same as [`Self::write`], with `label` absorbed first. The label is not
+    /// part of the proof.
+    pub fn write_labeled(&mut self, label: &str, value: Fr) {
+        self.sponge.absorb_label(label_value(label));
+        self.write(value);
+    }
+
     fn write_fp(&mut self, value: Fq) {
         // The base field is ever so slightly larger than the scalar field.
         // Assuming uniform random, the probability of overflow is 2^-127.
@@ -59,24 +231,71 @@ impl Prover {
     }
 }
 
+impl<S: DuplexSponge> Default for Prover<S> {
+    fn default() -> Self {
+        Prover::with_sponge(S::default())
+    }
+}
+
 impl<'a> Verifier<'a> {
     pub fn new(proof: &'a [Fr]) -> Self {
+        Self::with_sponge(Sponge::new(), proof)
+    }
+}
+
+impl<'a, S: DuplexSponge> Verifier<'a, S> {
+    pub fn with_sponge(sponge: S, proof: &'a [Fr]) -> Self {
         Self {
-            sponge: Sponge::new(),
-            proof: proof,
+            sponge,
+            bits: Bits::default(),
+            proof,
         }
     }
 
+    /// Transcript starting from `separator`, see [`Prover::with_domain`].
+    pub fn with_domain(separator: &DomainSeparator, proof: &'a [Fr]) -> Self {
+        Self::with_sponge(S::with_iv(separator.iv()), proof)
+    }
+
     pub fn generate(&mut self) -> Fr {
         self.sponge.squeeze()
     }
 
+    /// Counterpart of [`Prover::read_labeled`].
+    pub fn generate_labeled(&mut self, label: &str) -> Fr {
+        self.bits.clear();
+        self.sponge.absorb_label(label_value(label));
+        self.generate()
+    }
+
+    /// Counterpart of [`Prover::read_integer`].
+    pub fn generate_integer(&mut self, n: usize) -> usize {
+        self.bits.integer(&mut self.sponge, n)
+    }
+
+    /// Counterpart of [`Prover::read_bits`].
+    pub fn generate_bits(&mut self, count: usize) -> Vec<bool> {
+        self.bits.bits(&mut self.sponge, count)
+    }
+
+    /// Counterpart of [`Prover::read_indices`].
+    pub fn generate_indices(&mut self, n: usize, count: usize) -> Vec<usize> {
+        self.bits.indices(&mut self.sponge, n, count)
+    }
+
     pub fn read(&mut self) -> Fr {
         let value = self.reveal();
+        self.bits.clear();
         self.sponge.absorb(value);
         value
     }
 
+    /// Counterpart of [`Prover::write_labeled`].
+    pub fn read_labeled(&mut self, label: &str) -> Fr {
+        self.sponge.absorb_label(label_value(label));
+        self.read()
+    }
+
     pub fn read_fq(&mut self) -> Fq {
         let value = self.read().into_bigint();
         Fq::from_bigint(value).expect("Scalarfield always fits basefield.")
@@ -100,3 +319,132 @@ impl<'a> Verifier<'a> {
         *value
     }
 }
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::hash::Keccak256};
+
+    fn challenge(separator: &DomainSeparator) -> Fr {
+        let mut transcript: Prover = Prover::with_domain(separator);
+        transcript.write(Fr::from(1));
+        transcript.read()
+    }
+
+    #[test]
+    fn test_domain_separator() {
+        let separator = DomainSeparator::new("ligero", 1, &[Fr::from(128)]);
+        let challenges = [
+            challenge(&separator),
+            challenge(&DomainSeparator::new("hyrax", 1, &[Fr::from(128)])),
+            challenge(&DomainSeparator::new("ligero", 2, &[Fr::from(128)])),
+            challenge(&DomainSeparator::new("ligero", 1, &[Fr::from(100)])),
+            challenge(&DomainSeparator::new("ligero", 1, &[])),
+            {
+                let mut transcript = Prover::new();
+                transcript.write(Fr::from(1));
+                transcript.read()
+            },
+            // The IV is out of reach of writes.
+            {
+                let mut transcript = Prover::new();
+                separator
+                    .elements()
+                    .for_each(|value| transcript.write(value));
+                transcript.write(Fr::from(1));
+                transcript.read()
+            },
+            {
+                let mut transcript = Prover::new();
+                transcript.write(separator.iv());
+                transcript.write(Fr::from(1));
+                transcript.read()
+            },
+        ];
+        for (i, a) in challenges.iter().enumerate() {
+            assert!(challenges[..i].iter().all(|b| a != b));
+        }
+    }
+
+    #[test]
+    fn test_labels() {
+        let separator = DomainSeparator::new("test", 1, &[]);
+        let mut transcript = Prover::<ByteSponge<Keccak256>>::with_domain(&separator);
+        transcript.write_labeled("commitment", Fr::from(5));
+        let r = transcript.read_labeled("challenge");
+        transcript.write(r);
+        let proof = transcript.finish();
+        assert_eq!(proof.len(), 2);
+
+        let mut transcript = Verifier::<ByteSponge<Keccak256>>::with_domain(&separator, &proof);
+        assert_eq!(transcript.read_labeled("commitment"), Fr::from(5));
+        assert_eq!(transcript.generate_labeled("challenge"), r);
+        assert_eq!(transcript.read(), r);
+
+        // Labels are part of the transcript.
+        let mut transcript = Verifier::<ByteSponge<Keccak256>>::with_domain(&separator, &proof);
+        assert_eq!(transcript.read_labeled("other"), Fr::from(5));
+        assert_ne!(transcript.generate_labeled("challenge"), r);
+        let mut transcript = Verifier::<ByteSponge<Keccak256>>::with_domain(&separator, &proof);
+        transcript.read();
+        assert_ne!(transcript.generate(), r);
+    }
+
+    #[test]
+    fn test_label_marker() {
+        fn challenge<S: DuplexSponge>(writes: &[Fr], label: bool) -> Fr {
+            let mut transcript = Prover::<S>::default();
+            if label {
+                transcript.write_labeled("label", Fr::from(5));
+            }
+            writes.iter().for_each(|&value| transcript.write(value));
+            transcript.read()
+        }
+        fn check<S: DuplexSponge>() {
+            let labeled = challenge::<S>(&[], true);
+            let mut encoded: Vec<_> = byte_elements(b"label").collect();
+            encoded.push(Fr::from(5));
+            assert_ne!(labeled, challenge::<S>(&encoded, false));
+            assert_ne!(
+                labeled,
+                challenge::<S>(&[label_value("label"), Fr::from(5)], false)
+            );
+            assert_ne!(labeled, challenge::<S>(&[Fr::from(5)], false));
+            assert_eq!(labeled, challenge::<S>(&[], true));
+        }
+        check::<Sponge>();
+        check::<ByteSponge<Keccak256>>();
+    }
+
+    #[test]
+    fn test_small_challenges() {
+        let mut transcript = Prover::new();
+        transcript.write(Fr::from(3));
+        let integers: Vec<_> = (0..1000).map(|_| transcript.read_integer(10)).collect();
+        let bits = transcript.read_bits(100);
+        let indices = transcript.read_indices(100, 30);
+        transcript.write(Fr::from(4));
+        let after = transcript.read_integer(1 << 20);
+        let proof = transcript.finish();
+
+        // All residues show up about equally often.
+        assert!(integers.iter().all(|&i| i < 10));
+        for i in 0..10 {
+            let count = integers.iter().filter(|&&j| j == i).count();
+            assert!((50..150).contains(&count));
+        }
+        assert!(bits.iter().any(|b| *b) && bits.iter().any(|b| !*b));
+        assert_eq!(indices.len(), 30);
+        assert!(indices.windows(2).all(|w| w[0] < w[1]) && indices[29] < 100);
+        assert_eq!(Prover::new().read_indices(5, 5), [0, 1, 2, 3, 4]);
+
+        let mut transcript = Verifier::new(&proof);
+        transcript.read();
+        assert!(integers
+            .iter()
+            .all(|&i| transcript.generate_integer(10) == i));
+        assert_eq!(transcript.generate_bits(100), bits);
+        assert_eq!(transcript.generate_indices(100, 30), indices);
+        transcript.read();
+        assert_eq!(transcript.generate_integer(1 << 20), after);
+    }
+}
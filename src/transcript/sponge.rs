@@ -1,7 +1,7 @@
-use {crate::poseidon, ark_bn254::Fr, ark_ff::MontFp};
+use {super::DuplexSponge, crate::poseidon, ark_bn254::Fr, ark_ff::MontFp};
 
 // Random initial state (nothing up my sleeve: digits of 2 * pi in groups of 77 digits)
-const INITIAL_STATE: [Fr; 3] = [
+pub const INITIAL_STATE: [Fr; 3] = [
     MontFp!("62831853071795864769252867665590057683943387987502116419498891846156328125724"),
     MontFp!("17997256069650684234135964296173026564613294187689219101164463450718816256962"),
     MontFp!("23490056820540387704221111928924589790986076392885762195133186689225695129646"),
@@ -28,6 +28,15 @@ impl Sponge {
         }
     }
 
+    /// Sponge with `iv` added to the capacity. The state is marked full, so
+    /// the first absorb or squeeze permutes it.
+    pub fn with_iv(iv: Fr) -> Self {
+        let mut sponge = Self::new();
+        sponge.state[2] += iv;
+        sponge.sponge = SpongeState::Full;
+        sponge
+    }
+
     pub fn absorb(&mut self, value: Fr) {
         match self.sponge {
             SpongeState::Initial => {
@@ -46,6 +55,13 @@ impl Sponge {
         }
     }
 
+    /// Add `label` to the capacity, which absorbs never touch, and mark the
+    /// state full so it is permuted before further use.
+    pub fn absorb_label(&mut self, label: Fr) {
+        self.state[2] += label;
+        self.sponge = SpongeState::Full;
+    }
+
     pub fn squeeze(&mut self) -> Fr {
         match self.sponge {
             SpongeState::Initial => {
@@ -70,3 +86,21 @@ impl Default for Sponge {
         Sponge::new()
     }
 }
+
+impl DuplexSponge for Sponge {
+    fn with_iv(iv: Fr) -> Self {
+        Sponge::with_iv(iv)
+    }
+
+    fn absorb(&mut self, value: Fr) {
+        Sponge::absorb(self, value);
+    }
+
+    fn absorb_label(&mut self, label: Fr) {
+        Sponge::absorb_label(self, label);
+    }
+
+    fn squeeze(&mut self) -> Fr {
+        Sponge::squeeze(self)
+    }
+}